@@ -7,7 +7,7 @@ use esp_idf_svc::{
         self, cpu::Core, gpio::{self, Gpio4, InterruptType, PinDriver, Pull}, peripheral::Peripheral, prelude::Peripherals, spi::{
             self, SpiDriver, SpiDriverConfig, SPI2
         }, task::{queue::Queue, thread::ThreadSpawnConfiguration}, timer::{TimerDriver, config::Config as TimerConfig}, units::FromValueType
-    }, wifi::EspWifi
+    }, sntp::{EspSntp, SyncStatus}, wifi::EspWifi
 };
 use smart_leds::RGB8;
 mod spi_driver;
@@ -22,6 +22,7 @@ type LEDIter = [RGB8; 44];
 
 
 static mut WIFI_CONNECTION: Option<Box<EspWifi<'static>>> = None;
+static mut SNTP: Option<EspSntp<'static>> = None;
 static mut ERROR_STRING: Option<String> = None;
 fn main() -> Result<()>{
     // It is necessary to call this function once. Otherwise some patches to the runtime
@@ -61,6 +62,7 @@ enum AppShellCommand {
     RenderLEDs,
     MotionSensed,
     WifiConnected,
+    TimeSynced,
     Error
 }
 struct AppShell<'a> {
@@ -80,7 +82,9 @@ impl AppShell<'_>
         timer00: impl Peripheral<P = T0> + 'a, 
         timer01: impl Peripheral<P = T1> + 'a, 
         motion_sensor_pin: Gpio4)-> Result<AppShell<'a>> {
-        let app_state = AppState::new(duration_since_epoch());
+        let mut app_state = AppState::new(duration_since_epoch(), Self::create_provider(&CONFIG));
+        app_state.set_time_zone(TimeZone::new(CONFIG.utc_offset_minutes, CONFIG.us_daylight_saving));
+        app_state.set_display_config(Self::create_display_config(&CONFIG));
         let command_queue = Queue::new(200);
         let fetch_schedule_timer = Self::create_command_timer(timer00, AppShellCommand::FetchSchedule, &command_queue, false)?;
         let render_led_timer = Self::create_command_timer(timer01, AppShellCommand::RenderLEDs, &command_queue, true)?;
@@ -89,6 +93,51 @@ impl AppShell<'_>
         Ok(shell)
     }
 
+    fn create_provider(app_config: &Config) -> Box<dyn TransitProvider> {
+        match app_config.provider {
+            "gtfs-rt" => {
+                let stop_ids = split_list(app_config.gtfs_rt_stop_ids);
+                let route_ids = split_list(app_config.gtfs_rt_route_ids);
+                Box::new(GtfsRtProvider::new(app_config.gtfs_rt_url, stop_ids, route_ids))
+            }
+            "511" => {
+                let line_refs = split_list(app_config.siri_line_refs);
+                Box::new(SiriProvider::new(app_config.siri_api_key, app_config.siri_agency, app_config.siri_stop_code, line_refs))
+            }
+            _ => {
+                let destinations = app_config.destinations.parse().unwrap_or(DestinationFilter::All);
+                let station_config = StationConfig::new(app_config.origin_station, destinations);
+                Box::new(BartProvider::new(app_config.bart_api_key, station_config))
+            }
+        }
+    }
+
+    fn create_display_config(app_config: &Config) -> DisplayConfig {
+        let palette = app_config.route_colors.parse().unwrap_or_default();
+        let mode = match app_config.display_mode {
+            "split" => match (app_config.inner_ring.parse(), app_config.outer_ring.parse()) {
+                (Ok(inner), Ok(outer)) => DisplayMode::Split { inner, outer },
+                (Err(error), _) | (_, Err(error)) => {
+                    log::error!("Bad split ring config, showing the next two trains: {}", error);
+                    DisplayMode::NextTwo
+                }
+            },
+            "timeline" => DisplayMode::Timeline,
+            "headway" => DisplayMode::Headway,
+            _ => DisplayMode::NextTwo,
+        };
+        let overflow = app_config.overflow.parse().unwrap_or_else(|error| {
+            log::error!("Bad overflow config, truncating: {}", error);
+            OverflowStrategy::Truncate
+        });
+        let time_scale = app_config.time_scale.parse().unwrap_or_else(|error| {
+            log::error!("Bad time scale config, one minute per LED: {}", error);
+            TimeScale::Linear
+        });
+        let walk_time = WalkTime::new(app_config.walk_time_minutes, app_config.run_time_minutes);
+        DisplayConfig { palette, train_details: app_config.show_train_details, mode, overflow, time_scale, walk_time, leave_now_min: app_config.leave_now_minutes }
+    }
+
     fn start_command_pump(&mut self) {
         loop {
             if let Some((command, _)) = self.command_queue.recv_front(1000) {
@@ -112,6 +161,10 @@ impl AppShell<'_>
             AppShellCommand::WifiConnected => {
                 self.schedule_next_fetch(0)?;
             }
+            AppShellCommand::TimeSynced => {
+                let current_time_microsec = self.fetch_schedule_timer.counter()?;
+                self.app_state.time_synced(duration_since_epoch(), current_time_microsec);
+            }
             AppShellCommand::MotionSensed => {
                 let was_sleeping = self.app_state.motion_sensed(duration_since_epoch());
                 self.start_motion_sensor()?;
@@ -159,8 +212,21 @@ impl AppShell<'_>
         log::info!("Fetching schedule");
         let fetch_start_time_microsec = self.fetch_schedule_timer.counter()?;
         self.app_state.network_activity_started(fetch_start_time_microsec);  
-        let result = http::get("https://api.bart.gov/api/etd.aspx?cmd=etd&orig=ROCK&key=MW9S-E7SL-26DU-VV8V&json=y");
+        let result = http::get(self.app_state.departures_url());
         let next_fetch_sec = self.app_state.received_http_response(result, self.fetch_schedule_timer.counter()?);
+        //Before the first train there are no ETDs, the timetable can at least say when service starts
+        if let Some(schedule_url) = self.app_state.schedule_url() {
+            let result = http::get(schedule_url);
+            self.app_state.received_schedule_response(result, self.fetch_schedule_timer.counter()?);
+        }
+        if let Some(advisories_url) = self.app_state.advisories_url(self.fetch_schedule_timer.counter()?) {
+            let result = http::get(advisories_url);
+            self.app_state.received_advisories_response(result, self.fetch_schedule_timer.counter()?);
+        }
+        if let Some(elevators_url) = self.app_state.elevators_url(self.fetch_schedule_timer.counter()?) {
+            let result = http::get(elevators_url);
+            self.app_state.received_elevators_response(result, self.fetch_schedule_timer.counter()?);
+        }
         self.schedule_next_fetch(next_fetch_sec)?;
         Ok(())
     }
@@ -240,6 +306,18 @@ impl AppShell<'_>
                             WIFI_CONNECTION = Some(wifi_connection);
                             c.send_back(AppShellCommand::WifiConnected, 100).unwrap();
                         }
+                        //The static timetable needs the wall clock, live data doesn't, so this happens after the first fetch is queued
+                        match Self::sync_time() {
+                            Ok(sntp) => {
+                                unsafe {
+                                    SNTP = Some(sntp);
+                                    c.send_back(AppShellCommand::TimeSynced, 100).unwrap();
+                                }
+                            },
+                            Err(error) => {
+                                log::error!("Failed to sync time {:?}", error);
+                            }
+                        }
                     },
                     Err(error) => {
                         log::error!("Failed to connect to wifi {:?}", error);
@@ -249,6 +327,18 @@ impl AppShell<'_>
         Ok(())
     }
 
+    fn sync_time() -> Result<EspSntp<'static>> {
+        let sntp = EspSntp::new_default()?;
+        for _ in 0..30 {
+            if sntp.get_sync_status() == SyncStatus::Completed {
+                log::info!("Time synced");
+                return Ok(sntp);
+            }
+            std::thread::sleep(std::time::Duration::from_secs(1));
+        }
+        anyhow::bail!("SNTP timed out")
+    }
+
     fn new_queue(command_queue: &Queue<AppShellCommand>) -> Queue<AppShellCommand> {
         unsafe {
             Queue::new_borrowed(command_queue.as_raw())
@@ -313,6 +403,13 @@ impl LEDOutput {
 }
 
 
+fn split_list(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
 fn duration_since_epoch() -> Duration {
     SystemTime::now().duration_since(UNIX_EPOCH).unwrap()
 }
@@ -323,6 +420,72 @@ pub struct Config {
     wifi_ssid: &'static str,
     #[default("")]
     wifi_psk: &'static str,
+    #[default("MW9S-E7SL-26DU-VV8V")]
+    bart_api_key: &'static str,
+    #[default("ROCK")]
+    origin_station: &'static str,
+    // "*" for all destinations, "MLBR,SFIA" to allow only those, "!ANTC,PITT" to hide those
+    #[default("MLBR,SFIA")]
+    destinations: &'static str,
+    // "bart", "gtfs-rt" or "511". Only one at a time, both rings show the same feed,
+    // so BART on one ring and a bus stop on the other isn't possible yet
+    #[default("bart")]
+    provider: &'static str,
+    #[default("")]
+    gtfs_rt_url: &'static str,
+    // Comma separated, e.g. "55989,55990"
+    #[default("")]
+    gtfs_rt_stop_ids: &'static str,
+    // Comma separated, empty for every route serving the stops
+    #[default("")]
+    gtfs_rt_route_ids: &'static str,
+    #[default("")]
+    siri_api_key: &'static str,
+    // 511.org operator id, e.g. "SF" for Muni or "AC" for AC Transit
+    #[default("SF")]
+    siri_agency: &'static str,
+    #[default("")]
+    siri_stop_code: &'static str,
+    // Comma separated, empty for every line serving the stop
+    #[default("")]
+    siri_line_refs: &'static str,
+    // Wall clock for the offline timetable, standard time offset from UTC
+    #[default(-480)]
+    utc_offset_minutes: i32,
+    // Follow the US daylight saving dates
+    #[default(true)]
+    us_daylight_saving: bool,
+    // Comma separated overrides by destination or line, e.g. "SFIA=#00ffff,RED=#ff00ff". Empty uses the API's line colors
+    #[default("")]
+    route_colors: &'static str,
+    // Green LED past the countdown for trains that take bikes, half brightness for trains under 10 cars
+    #[default(false)]
+    show_train_details: bool,
+    // "next" for the next two trains, "split" to give each ring its own direction or platform,
+    // "timeline" for the next train inside and a tick for every train in the next 24 minutes outside,
+    // "headway" for the next train inside and the gaps between the ones after it outside
+    #[default("next")]
+    display_mode: &'static str,
+    // Split mode ring filters: "north", "south", "inbound", "outbound" or "platform:2"
+    #[default("north")]
+    inner_ring: &'static str,
+    #[default("south")]
+    outer_ring: &'static str,
+    // Countdowns longer than a ring: "truncate" shows a full ring, "wrap" starts a second lap in purple
+    #[default("truncate")]
+    overflow: &'static str,
+    // Minutes per LED on both rings: "linear", a number like "2", or "log" to fit an hour on a ring
+    #[default("linear")]
+    time_scale: &'static str,
+    // Minutes from the display to the platform, the rings count down to when to leave and hide trains that can't be caught
+    #[default(0)]
+    walk_time_minutes: u32,
+    // Minutes running saves over walking, trains that can only be caught running blink
+    #[default(0)]
+    run_time_minutes: u32,
+    // Pulse the inner ring, faster as it gets closer, once it's this many minutes until time to leave. 0 turns it off
+    #[default(0)]
+    leave_now_minutes: u32,
 }
 
 
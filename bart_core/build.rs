@@ -0,0 +1,90 @@
+//Compiles a slice of a static GTFS feed into flash so there's something to show when live data is down.
+//Point STATIC_GTFS_ZIP at a feed (e.g. https://www.bart.gov/dev/schedules/google_transit.zip), without it the timetable is empty.
+//The week compiled in starts today, or on STATIC_GTFS_DATE (YYYYMMDD), so rebuild when the agency changes its schedule.
+use std::{env, fs, io::Read, path::Path, time::SystemTime};
+
+#[allow(dead_code)]
+#[path = "src/time.rs"]
+mod time;
+#[path = "src/static_gtfs.rs"]
+mod static_gtfs;
+use static_gtfs::{build_timetable, parse_date, CompactTimetable, GtfsFilter};
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=STATIC_GTFS_ZIP");
+    println!("cargo:rerun-if-env-changed=STATIC_GTFS_STOPS");
+    println!("cargo:rerun-if-env-changed=STATIC_GTFS_DIRECTIONS");
+    println!("cargo:rerun-if-env-changed=STATIC_GTFS_DATE");
+    println!("cargo:rerun-if-env-changed=STATIC_GTFS_DIRECTION_NAMES");
+    println!("cargo:rerun-if-changed=src/static_gtfs.rs");
+    println!("cargo:rerun-if-changed=src/time.rs");
+
+    let timetable = match env::var("STATIC_GTFS_ZIP") {
+        Ok(zip_path) if !zip_path.is_empty() => {
+            println!("cargo:rerun-if-changed={}", zip_path);
+            let filter = GtfsFilter {
+                stop_ids: list_var("STATIC_GTFS_STOPS"),
+                direction_ids: list_var("STATIC_GTFS_DIRECTIONS").iter().filter_map(|id| id.parse().ok()).collect(),
+            };
+            let first_day = match env::var("STATIC_GTFS_DATE") {
+                Ok(date) if !date.is_empty() => parse_date(&date).unwrap_or_else(|| panic!("STATIC_GTFS_DATE {} isn't YYYYMMDD", date)),
+                _ => SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs() as i64 / 86400,
+            };
+            timetable_from_zip(&zip_path, &filter, first_day).unwrap_or_else(|error| panic!("Couldn't read {}: {}", zip_path, error))
+        }
+        _ => CompactTimetable::default(),
+    };
+    let out_path = Path::new(&env::var("OUT_DIR").unwrap()).join("static_timetable.rs");
+    fs::write(out_path, timetable.to_rust_source() + &directions_source()).unwrap();
+}
+
+/// What direction_id 0 and 1 mean, so split mode can tell the scheduled trains apart the way the live feed does.
+/// BART's live feed says North and South, GTFS leaves the meaning up to the agency.
+fn directions_source() -> String {
+    let names = match list_var("STATIC_GTFS_DIRECTION_NAMES") {
+        names if names.is_empty() => vec!["outbound".to_string(), "inbound".to_string()],
+        names => names,
+    };
+    let directions: Vec<&str> = names.iter()
+        .map(|name| match name.to_ascii_lowercase().as_str() {
+            "north" => "Direction::North",
+            "south" => "Direction::South",
+            "inbound" => "Direction::Inbound",
+            "outbound" => "Direction::Outbound",
+            _ => panic!("STATIC_GTFS_DIRECTION_NAMES: unknown direction {}", name),
+        })
+        .collect();
+    if directions.len() != 2 {
+        panic!("STATIC_GTFS_DIRECTION_NAMES needs a direction for direction_id 0 and 1");
+    }
+    format!("static DIRECTIONS: [Direction; 2] = [{}];\n", directions.join(", "))
+}
+
+fn timetable_from_zip(zip_path: &str, filter: &GtfsFilter, first_day: i64) -> Result<CompactTimetable, String> {
+    let file = fs::File::open(zip_path).map_err(|error| error.to_string())?;
+    let mut archive = zip::ZipArchive::new(file).map_err(|error| error.to_string())?;
+    let mut read = |name: &str| -> Result<String, String> {
+        let mut contents = String::new();
+        archive.by_name(name)
+            .map_err(|error| format!("{}: {}", name, error))?
+            .read_to_string(&mut contents)
+            .map_err(|error| format!("{}: {}", name, error))?;
+        Ok(contents)
+    };
+    let stops = read("stops.txt")?;
+    let trips = read("trips.txt")?;
+    let stop_times = read("stop_times.txt")?;
+    let calendar = read("calendar.txt")?;
+    //Optional in the spec, BART uses it for holidays
+    let calendar_dates = read("calendar_dates.txt").unwrap_or_default();
+    build_timetable(&stops, &trips, &stop_times, &calendar, &calendar_dates, filter, first_day)
+}
+
+fn list_var(name: &str) -> Vec<String> {
+    env::var(name)
+        .unwrap_or_default()
+        .split(',')
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
@@ -0,0 +1,18 @@
+/// A service advisory, e.g. a delay or a single tracking notice.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Advisory {
+    pub id: String,
+    /// Station abbreviation, "BART" for the whole system
+    pub station: String,
+    /// "DELAY", "EMERGENCY"...
+    pub kind: String,
+    pub description: String,
+}
+
+/// An elevator that's out of service at a station on the configured trip.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ElevatorOutage {
+    /// Station abbreviation
+    pub station: String,
+    pub description: String,
+}
@@ -0,0 +1,80 @@
+/// Something the API told us alongside (or instead of) the departures.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApiNotice {
+    Message(String),
+    Warning(String),
+    Error { text: String, details: String },
+}
+
+impl ApiNotice {
+    pub fn is_error(&self) -> bool {
+        matches!(self, ApiNotice::Error { .. })
+    }
+}
+
+/// Why a fetch didn't produce departures, grouped by how we should react to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApiError {
+    /// Never got an HTTP response (DNS, TLS, WiFi dropped...)
+    Transport(String),
+    /// HTTP 429
+    RateLimited,
+    /// Any other 4xx
+    ClientError(u16),
+    /// 5xx or anything else that isn't a 2xx
+    ServerError(u16),
+    InvalidApiKey,
+    UnknownStation,
+    /// The API understood us but didn't like the request
+    MalformedRequest(String),
+    /// The response didn't look like what we expected
+    Schema(String),
+}
+
+impl ApiError {
+    pub fn from_status(status: u16) -> ApiError {
+        match status {
+            429 => ApiError::RateLimited,
+            400..=499 => ApiError::ClientError(status),
+            _ => ApiError::ServerError(status),
+        }
+    }
+
+    /// Errors that won't go away until someone changes the configuration
+    pub fn is_configuration_error(&self) -> bool {
+        matches!(self, ApiError::InvalidApiKey | ApiError::UnknownStation | ApiError::MalformedRequest(_) | ApiError::ClientError(_))
+    }
+}
+
+impl core::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ApiError::Transport(error) => write!(f, "transport error: {}", error),
+            ApiError::RateLimited => write!(f, "rate limited"),
+            ApiError::ClientError(status) => write!(f, "unexpected response code: {}", status),
+            ApiError::ServerError(status) => write!(f, "unexpected response code: {}", status),
+            ApiError::InvalidApiKey => write!(f, "invalid or expired API key"),
+            ApiError::UnknownStation => write!(f, "unknown station"),
+            ApiError::MalformedRequest(error) => write!(f, "malformed request: {}", error),
+            ApiError::Schema(error) => write!(f, "unexpected response: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+//The firmware's http client reports everything through anyhow, non-2xx responses arrive as a wrapped ApiError
+impl From<anyhow::Error> for ApiError {
+    fn from(error: anyhow::Error) -> Self {
+        match error.downcast::<ApiError>() {
+            Ok(api_error) => api_error,
+            Err(error) => ApiError::Transport(format!("{:?}", error)),
+        }
+    }
+}
+
+impl From<serde_json::Error> for ApiError {
+    fn from(error: serde_json::Error) -> Self {
+        ApiError::Schema(error.to_string())
+    }
+}
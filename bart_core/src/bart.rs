@@ -0,0 +1,227 @@
+use serde::{Deserialize, Deserializer};
+use smart_leds::RGB8;
+use std::str::FromStr;
+
+use crate::bart_advisory::{advisories_url, elevators_url, parse_advisories, parse_elevator_outages};
+use crate::bart_schedule::{parse_station_schedule, station_schedule_url};
+use crate::palette::parse_hexcolor;
+use crate::time::{parse_bart_timestamp, LocalTime};
+use crate::{Advisory, ApiError, ApiNotice, Departure, DepartureState, Direction, Duration, ElevatorOutage, FeedSnapshot, StationConfig, TransitProvider};
+
+/// Real-time departures from api.bart.gov for one origin station.
+pub struct BartProvider {
+    api_key: String,
+    station_config: StationConfig,
+}
+
+impl BartProvider {
+    pub fn new(api_key: &str, station_config: StationConfig) -> BartProvider {
+        BartProvider { api_key: api_key.to_string(), station_config }
+    }
+}
+
+impl TransitProvider for BartProvider {
+    fn departures_url(&self) -> String {
+        format!("https://api.bart.gov/api/etd.aspx?cmd=etd&orig={}&key={}&json=y", self.station_config.origin, self.api_key)
+    }
+
+    fn parse_departures(&self, payload: &[u8]) -> Result<FeedSnapshot, ApiError> {
+        let json: Top = serde_json::from_slice(payload)?;
+        log::info!("JSON: {:?}", json);
+        check_notices(&json.root.message)?;
+        let departures = json.root
+            .station
+            .into_iter()
+            .filter(|station| {
+                station.abbr.eq_ignore_ascii_case(&self.station_config.origin)
+            })
+            .flat_map(|station| {
+                station.etd
+            })
+            .filter(|etd| {
+                self.station_config.destinations.matches(&etd.abbreviation)
+            })
+            .flat_map(|etd| {
+                let Etd { destination, abbreviation, estimate } = etd;
+                estimate.into_iter().filter_map(move |esd| {
+                    esd.into_departure(&destination, &abbreviation)
+                })
+            })
+            .collect::<Vec<Departure>>();
+        let response_unix_sec = parse_bart_timestamp(&json.root.date, &json.root.time);
+        Ok(FeedSnapshot { departures, notices: json.root.message, response_unix_sec })
+    }
+
+    fn shows_destination(&self, abbreviation: &str) -> bool {
+        self.station_config.destinations.matches(abbreviation)
+    }
+
+    fn schedule_url(&self) -> Option<String> {
+        Some(station_schedule_url(&self.api_key, &self.station_config))
+    }
+
+    fn parse_schedule(&self, payload: &[u8], now: LocalTime) -> Result<Vec<Departure>, ApiError> {
+        parse_station_schedule(payload, &self.station_config, now)
+    }
+
+    fn advisories_url(&self) -> Option<String> {
+        Some(advisories_url(&self.api_key, &self.station_config))
+    }
+
+    fn parse_advisories(&self, payload: &[u8]) -> Result<Vec<Advisory>, ApiError> {
+        parse_advisories(payload, &self.station_config)
+    }
+
+    fn elevators_url(&self) -> Option<String> {
+        Some(elevators_url(&self.api_key))
+    }
+
+    fn parse_elevator_outages(&self, payload: &[u8]) -> Result<Vec<ElevatorOutage>, ApiError> {
+        parse_elevator_outages(payload, &self.station_config)
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct Top {
+    root: Root
+}
+
+//Late at night BART leaves out `etd` (or even `station`) and explains why in `message`
+#[derive(Deserialize, Debug)]
+struct Root {
+    #[serde(default)]
+    date: String,
+    #[serde(default)]
+    time: String,
+    #[serde(default)]
+    station: Vec<Station>,
+    #[serde(default, deserialize_with = "notices")]
+    message: Vec<ApiNotice>
+}
+
+
+#[derive(Deserialize, Debug)]
+struct Station {
+    abbr: String,
+    #[serde(default)]
+    etd: Vec<Etd>
+}
+
+#[derive(Deserialize, Debug)]
+struct Etd {
+    destination: String,
+    abbreviation: String,
+    #[serde(default)]
+    estimate: Vec<Estimate>
+}
+
+#[derive(Deserialize, Debug)]
+struct Estimate {
+    minutes: String,
+    #[serde(deserialize_with = "from_str")]
+    platform: u8,
+    direction: Direction,
+    #[serde(deserialize_with = "from_str")]
+    length: u8,
+    color: String,
+    #[serde(deserialize_with = "hexcolor")]
+    hexcolor: Option<RGB8>,
+    #[serde(deserialize_with = "flag")]
+    bikeflag: bool,
+    #[serde(deserialize_with = "seconds")]
+    delay: Duration,
+    #[serde(deserialize_with = "flag")]
+    cancelflag: bool,
+}
+
+impl Estimate {
+    fn into_departure(self, destination: &str, abbreviation: &str) -> Option<Departure> {
+        let state = if self.cancelflag {
+            DepartureState::Cancelled
+        } else if self.minutes.eq_ignore_ascii_case("leaving") {
+            DepartureState::Leaving
+        } else {
+            DepartureState::Minutes(self.minutes.parse::<u32>().ok()?)
+        };
+        Some(Departure {
+            destination: destination.to_string(),
+            abbreviation: abbreviation.to_string(),
+            state,
+            platform: self.platform,
+            direction: self.direction,
+            car_count: self.length,
+            color: self.color,
+            hexcolor: self.hexcolor,
+            bike_allowed: self.bikeflag,
+            delay: self.delay,
+        })
+    }
+}
+
+//The BART API sends every value as a string, these convert them to the types we actually want
+fn from_str<'de, D: Deserializer<'de>, T: FromStr>(deserializer: D) -> Result<T, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    value.trim().parse().map_err(|_| serde::de::Error::custom(format!("unexpected value \"{}\"", value)))
+}
+
+pub(crate) fn flag<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    let value: u8 = from_str(deserializer)?;
+    Ok(value != 0)
+}
+
+fn seconds<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    let value: u64 = from_str(deserializer)?;
+    Ok(Duration::from_secs(value))
+}
+
+fn hexcolor<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<RGB8>, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    Ok(parse_hexcolor(&value))
+}
+
+//`message` is an empty string when all is well, otherwise an object holding a warning and/or an error
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawMessage {
+    Text(String),
+    Notice {
+        warning: Option<String>,
+        error: Option<RawError>
+    }
+}
+
+#[derive(Deserialize)]
+struct RawError {
+    #[serde(default)]
+    text: String,
+    #[serde(default)]
+    details: String
+}
+
+/// BART reports bad requests inside a 200 response as `message.error.text`
+pub(crate) fn check_notices(notices: &[ApiNotice]) -> Result<(), ApiError> {
+    let Some(ApiNotice::Error { text, details }) = notices.iter().find(|notice| notice.is_error()) else {
+        return Ok(());
+    };
+    let text_lower = text.to_ascii_lowercase();
+    Err(if text_lower.contains("key") {
+        ApiError::InvalidApiKey
+    } else if text_lower.contains("orig") || text_lower.contains("station") {
+        ApiError::UnknownStation
+    } else {
+        ApiError::MalformedRequest(format!("{} {}", text, details))
+    })
+}
+
+pub(crate) fn notices<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<ApiNotice>, D::Error> {
+    let notices = match RawMessage::deserialize(deserializer)? {
+        RawMessage::Text(text) if text.trim().is_empty() => Vec::new(),
+        RawMessage::Text(text) => vec![ApiNotice::Message(text)],
+        RawMessage::Notice { warning, error } => {
+            let warning = warning.map(ApiNotice::Warning);
+            let error = error.map(|error| ApiNotice::Error { text: error.text, details: error.details });
+            warning.into_iter().chain(error).collect()
+        }
+    };
+    Ok(notices)
+}
@@ -0,0 +1,100 @@
+use serde::{Deserialize, Deserializer};
+
+use crate::bart::{check_notices, notices};
+use crate::bart_stations::mentions_station;
+use crate::{Advisory, ApiError, ApiNotice, DestinationFilter, ElevatorOutage, StationConfig};
+
+pub(crate) fn advisories_url(api_key: &str, station_config: &StationConfig) -> String {
+    format!("https://api.bart.gov/api/bsa.aspx?cmd=bsa&orig={}&key={}&json=y", station_config.origin, api_key)
+}
+
+/// Advisories from `cmd=bsa` that affect the origin station, system wide ones included.
+pub(crate) fn parse_advisories(payload: &[u8], station_config: &StationConfig) -> Result<Vec<Advisory>, ApiError> {
+    let json: Top = serde_json::from_slice(payload)?;
+    check_notices(&json.root.message)?;
+    let advisories = json.root
+        .bsa
+        .into_iter()
+        //"No delays reported." comes back as a bsa without an id
+        .filter_map(|bsa| {
+            let id = bsa.id?;
+            Some(Advisory { id, station: bsa.station, kind: bsa.kind, description: bsa.description })
+        })
+        .filter(|advisory| {
+            advisory.station.is_empty()
+                || advisory.station.eq_ignore_ascii_case("BART")
+                || advisory.station.eq_ignore_ascii_case(&station_config.origin)
+        })
+        .collect();
+    Ok(advisories)
+}
+
+pub(crate) fn elevators_url(api_key: &str) -> String {
+    format!("https://api.bart.gov/api/bsa.aspx?cmd=elev&key={}&json=y", api_key)
+}
+
+/// Outages from `cmd=elev` at the origin or at an allow-listed destination.
+/// The feed is one sentence listing every out of service elevator, so stations are found by name.
+pub(crate) fn parse_elevator_outages(payload: &[u8], station_config: &StationConfig) -> Result<Vec<ElevatorOutage>, ApiError> {
+    let json: Top = serde_json::from_slice(payload)?;
+    check_notices(&json.root.message)?;
+    let mut stations = vec![station_config.origin.clone()];
+    //A deny-list or "*" doesn't say where someone is going, only the origin is known then
+    if let DestinationFilter::Allow(destinations) = &station_config.destinations {
+        stations.extend(destinations.iter().cloned());
+    }
+    let outages = json.root
+        .bsa
+        .iter()
+        .flat_map(|bsa| {
+            stations.iter()
+                .filter(|station| mentions_station(&bsa.description, station))
+                .map(|station| ElevatorOutage { station: station.clone(), description: bsa.description.clone() })
+        })
+        .collect();
+    Ok(outages)
+}
+
+#[derive(Deserialize, Debug)]
+struct Top {
+    root: Root
+}
+
+#[derive(Deserialize, Debug)]
+struct Root {
+    #[serde(default)]
+    bsa: Vec<Bsa>,
+    #[serde(default, deserialize_with = "notices")]
+    message: Vec<ApiNotice>
+}
+
+#[derive(Deserialize, Debug)]
+struct Bsa {
+    #[serde(rename = "@id")]
+    id: Option<String>,
+    #[serde(default)]
+    station: String,
+    #[serde(rename = "type", default)]
+    kind: String,
+    #[serde(deserialize_with = "cdata")]
+    description: String,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawText {
+    Text(String),
+    Cdata {
+        #[serde(rename = "#cdata-section")]
+        text: String
+    }
+}
+
+//Free text comes wrapped in {"#cdata-section": "..."} since the JSON is converted from XML
+pub(crate) fn cdata<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    let text = match RawText::deserialize(deserializer)? {
+        RawText::Text(text) => text,
+        RawText::Cdata { text } => text,
+    };
+    Ok(text.trim().to_string())
+}
@@ -0,0 +1,113 @@
+use serde::{Deserialize, Deserializer};
+
+use crate::bart::{check_notices, flag, notices};
+use crate::time::LocalTime;
+use crate::{ApiError, ApiNotice, Departure, DepartureState, Direction, Duration, StationConfig};
+
+const MINUTES_PER_DAY: u32 = 1440;
+
+pub(crate) fn station_schedule_url(api_key: &str, station_config: &StationConfig) -> String {
+    format!("https://api.bart.gov/api/sched.aspx?cmd=stnsched&orig={}&key={}&json=y", station_config.origin, api_key)
+}
+
+/// Today's timetable for the origin from `cmd=stnsched`, soonest first relative to `now`.
+/// Returns the API's error notice as an error since there's nothing to fall back to from a fallback.
+pub(crate) fn parse_station_schedule(payload: &[u8], station_config: &StationConfig, now: LocalTime) -> Result<Vec<Departure>, ApiError> {
+    let json: Top = serde_json::from_slice(payload)?;
+    check_notices(&json.root.message)?;
+    let now_minute = now.minute_of_day();
+    let mut departures: Vec<Departure> = json.root
+        .station
+        .into_iter()
+        .filter(|station| station.abbr.eq_ignore_ascii_case(&station_config.origin))
+        .flat_map(|station| station.item)
+        .filter(|item| station_config.destinations.matches(&item.train_head_station))
+        .filter_map(|item| {
+            let minute = parse_clock(&item.orig_time)?;
+            //The schedule is for the whole service day, trains from earlier today wrap around to tomorrow
+            let minutes_until = (minute + MINUTES_PER_DAY - now_minute) % MINUTES_PER_DAY;
+            if minutes_until == 0 {
+                return None;
+            }
+            Some(item.into_departure(minutes_until))
+        })
+        .collect();
+    departures.sort_by_key(|departure| departure.state.minutes());
+    Ok(departures)
+}
+
+/// `5:04 AM` is 304, `12:15 AM` is 15
+fn parse_clock(value: &str) -> Option<u32> {
+    let (clock, meridiem) = value.trim().split_once(' ')?;
+    let (hours, minutes) = clock.split_once(':')?;
+    let hours: u32 = hours.parse().ok()?;
+    let minutes: u32 = minutes.parse().ok()?;
+    if hours == 0 || hours > 12 || minutes >= 60 {
+        return None;
+    }
+    let afternoon = if meridiem.eq_ignore_ascii_case("PM") { 12 } else { 0 };
+    Some((hours % 12 + afternoon) * 60 + minutes)
+}
+
+#[derive(Deserialize, Debug)]
+struct Top {
+    root: Root
+}
+
+#[derive(Deserialize, Debug)]
+struct Root {
+    #[serde(default, deserialize_with = "one_or_many")]
+    station: Vec<Station>,
+    #[serde(default, deserialize_with = "notices")]
+    message: Vec<ApiNotice>
+}
+
+#[derive(Deserialize, Debug)]
+struct Station {
+    abbr: String,
+    #[serde(default, deserialize_with = "one_or_many")]
+    item: Vec<Item>
+}
+
+//The schedule API is XML converted to JSON, so attributes keep their @ and lone items aren't wrapped in an array
+#[derive(Deserialize, Debug)]
+struct Item {
+    #[serde(rename = "@trainHeadStation")]
+    train_head_station: String,
+    #[serde(rename = "@origTime")]
+    orig_time: String,
+    #[serde(rename = "@bikeflag", default, deserialize_with = "flag")]
+    bikeflag: bool,
+}
+
+impl Item {
+    fn into_departure(self, minutes_until: u32) -> Departure {
+        Departure {
+            destination: self.train_head_station.clone(),
+            abbreviation: self.train_head_station,
+            state: DepartureState::Minutes(minutes_until),
+            platform: 0,
+            //stnsched doesn't say which way a train is headed or where it stops
+            direction: Direction::Unknown,
+            car_count: 0,
+            color: String::new(),
+            hexcolor: None,
+            bike_allowed: self.bikeflag,
+            delay: Duration::ZERO,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+fn one_or_many<'de, D: Deserializer<'de>, T: Deserialize<'de>>(deserializer: D) -> Result<Vec<T>, D::Error> {
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    })
+}
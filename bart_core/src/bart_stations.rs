@@ -0,0 +1,71 @@
+//BART station names as the API spells them, used to find stations in free text notices
+const STATIONS: [(&str, &str); 50] = [
+    ("12TH", "12th St. Oakland City Center"),
+    ("16TH", "16th St. Mission"),
+    ("19TH", "19th St. Oakland"),
+    ("24TH", "24th St. Mission"),
+    ("ANTC", "Antioch"),
+    ("ASHB", "Ashby"),
+    ("BALB", "Balboa Park"),
+    ("BAYF", "Bay Fair"),
+    ("BERY", "Berryessa/North San Jose"),
+    ("CAST", "Castro Valley"),
+    ("CIVC", "Civic Center/UN Plaza"),
+    ("COLM", "Colma"),
+    ("COLS", "Coliseum"),
+    ("CONC", "Concord"),
+    ("DALY", "Daly City"),
+    ("DBRK", "Downtown Berkeley"),
+    ("DELN", "El Cerrito del Norte"),
+    ("DUBL", "Dublin/Pleasanton"),
+    ("EMBR", "Embarcadero"),
+    ("FRMT", "Fremont"),
+    ("FTVL", "Fruitvale"),
+    ("GLEN", "Glen Park"),
+    ("HAYW", "Hayward"),
+    ("LAFY", "Lafayette"),
+    ("LAKE", "Lake Merritt"),
+    ("MCAR", "MacArthur"),
+    ("MLBR", "Millbrae"),
+    ("MLPT", "Milpitas"),
+    ("MONT", "Montgomery St."),
+    ("NBRK", "North Berkeley"),
+    ("NCON", "North Concord/Martinez"),
+    ("OAKL", "Oakland International Airport"),
+    ("ORIN", "Orinda"),
+    ("PCTR", "Pittsburg Center"),
+    ("PHIL", "Pleasant Hill/Contra Costa Centre"),
+    ("PITT", "Pittsburg/Bay Point"),
+    ("PLZA", "El Cerrito Plaza"),
+    ("POWL", "Powell St."),
+    ("RICH", "Richmond"),
+    ("ROCK", "Rockridge"),
+    ("SANL", "San Leandro"),
+    ("SBRN", "San Bruno"),
+    ("SFIA", "San Francisco International Airport"),
+    ("SHAY", "South Hayward"),
+    ("SSAN", "South San Francisco"),
+    ("UCTY", "Union City"),
+    ("WARM", "Warm Springs/South Fremont"),
+    ("WCRK", "Walnut Creek"),
+    ("WDUB", "West Dublin/Pleasanton"),
+    ("WOAK", "West Oakland"),
+];
+
+pub(crate) fn station_name(abbreviation: &str) -> Option<&'static str> {
+    STATIONS.iter()
+        .find(|(abbr, _)| abbr.eq_ignore_ascii_case(abbreviation))
+        .map(|(_, name)| *name)
+}
+
+/// Whether `text` mentions the station. Notices tend to shorten names, so `12th St. Oakland City Center`
+/// matches `12th St. Oakland` and `Dublin/Pleasanton` matches `Dublin`. That can also match a neighbour
+/// (`Dublin` is in `West Dublin`), which is the safe direction to be wrong in.
+pub(crate) fn mentions_station(text: &str, abbreviation: &str) -> bool {
+    let Some(name) = station_name(abbreviation) else {
+        return false;
+    };
+    let short_name = name.split('/').next().unwrap_or(name);
+    let short_name = short_name.strip_suffix(" City Center").unwrap_or(short_name);
+    text.to_ascii_lowercase().contains(&short_name.to_ascii_lowercase())
+}
@@ -0,0 +1,61 @@
+use std::str::FromStr;
+
+/// Which destinations leaving the origin station are shown on the rings.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DestinationFilter {
+    All,
+    Allow(Vec<String>),
+    Deny(Vec<String>),
+}
+
+impl DestinationFilter {
+    pub fn matches(&self, abbreviation: &str) -> bool {
+        match self {
+            DestinationFilter::All => true,
+            DestinationFilter::Allow(allowed) => allowed.iter().any(|abbr| abbr.eq_ignore_ascii_case(abbreviation)),
+            DestinationFilter::Deny(denied) => !denied.iter().any(|abbr| abbr.eq_ignore_ascii_case(abbreviation)),
+        }
+    }
+}
+
+/// Parses the config file form of a filter:
+/// `"*"` (or an empty list like `","`) shows everything, `"MLBR,SFIA"` is an allow-list and `"!ANTC,PITT"` is a deny-list.
+impl FromStr for DestinationFilter {
+    type Err = std::convert::Infallible;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        if value.is_empty() || value == "*" {
+            return Ok(DestinationFilter::All);
+        }
+        let (deny, list) = match value.strip_prefix('!') {
+            Some(list) => (true, list),
+            None => (false, value),
+        };
+        let abbreviations: Vec<String> = list
+            .split(',')
+            .map(|abbr| abbr.trim().to_ascii_uppercase())
+            .filter(|abbr| !abbr.is_empty())
+            .collect();
+        //An allow-list with nothing on it would hide every train, which is never what a stray comma meant
+        if abbreviations.is_empty() {
+            Ok(DestinationFilter::All)
+        } else if deny {
+            Ok(DestinationFilter::Deny(abbreviations))
+        } else {
+            Ok(DestinationFilter::Allow(abbreviations))
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StationConfig {
+    pub origin: String,
+    pub destinations: DestinationFilter,
+}
+
+impl StationConfig {
+    pub fn new(origin: &str, destinations: DestinationFilter) -> StationConfig {
+        StationConfig { origin: origin.trim().to_ascii_uppercase(), destinations }
+    }
+}
@@ -0,0 +1,47 @@
+use core::time::Duration;
+use serde::Deserialize;
+use smart_leds::RGB8;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    North,
+    South,
+    Inbound,
+    Outbound,
+    /// The feed doesn't say, split mode shows these on both rings
+    #[serde(skip)]
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepartureState {
+    Leaving,
+    Minutes(u32),
+    Cancelled,
+}
+
+impl DepartureState {
+    /// Minutes until the train leaves, a leaving train counts as zero and a cancelled one never leaves.
+    pub fn minutes(&self) -> Option<u32> {
+        match self {
+            DepartureState::Leaving => Some(0),
+            DepartureState::Minutes(minutes) => Some(*minutes),
+            DepartureState::Cancelled => None,
+        }
+    }
+}
+
+/// A single train leaving the origin station, with everything the API told us about it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Departure {
+    pub destination: String,
+    pub abbreviation: String,
+    pub state: DepartureState,
+    pub platform: u8,
+    pub direction: Direction,
+    pub car_count: u8,
+    pub color: String,
+    pub hexcolor: Option<RGB8>,
+    pub bike_allowed: bool,
+    pub delay: Duration,
+}
@@ -0,0 +1,108 @@
+use std::str::FromStr;
+
+use crate::{Departure, Direction, RoutePalette, TimeScale};
+
+/// How departures are drawn, set once from the config file.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DisplayConfig {
+    pub palette: RoutePalette,
+    /// Light the LED past a bike-friendly train's countdown green and draw trains shorter than 10 cars at half brightness
+    pub train_details: bool,
+    pub mode: DisplayMode,
+    pub overflow: OverflowStrategy,
+    pub time_scale: TimeScale,
+    pub walk_time: WalkTime,
+    /// Pulse the inner ring once the next catchable train is this many minutes from when to leave, zero for never
+    pub leave_now_min: u32,
+}
+
+/// How far the display is from the platform. Rings count down to when to leave rather than when the train does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WalkTime {
+    pub walk_min: u32,
+    /// How much sooner running gets there. A train inside this window blinks on the first LED instead of disappearing
+    pub run_min: u32,
+}
+
+impl WalkTime {
+    pub fn new(walk_min: u32, run_min: u32) -> WalkTime {
+        WalkTime { walk_min, run_min }
+    }
+}
+
+/// What the two rings count down to.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum DisplayMode {
+    /// The next train on the inner ring and the one after it on the outer ring
+    #[default]
+    NextTwo,
+    /// Each ring shows the next train matching its filter, e.g. northbound inside and southbound outside
+    Split { inner: RingFilter, outer: RingFilter },
+    /// The next train on the inner ring, the outer ring a timeline with a tick at every upcoming train's minute
+    Timeline,
+    /// The next train on the inner ring, the outer ring split into the gaps between the trains after it
+    Headway,
+}
+
+/// How a countdown longer than its ring is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowStrategy {
+    /// A full ring, 24 minutes and 45 minutes look the same
+    #[default]
+    Truncate,
+    /// A second lap in another color, so on the outer ring 30 minutes is a full ring with the first 6 LEDs recolored.
+    /// Anything past two laps is a full ring of the second color
+    Wrap,
+}
+
+/// Parses the config file form, `"truncate"` or `"wrap"`.
+impl FromStr for OverflowStrategy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "truncate" => Ok(OverflowStrategy::Truncate),
+            "wrap" => Ok(OverflowStrategy::Wrap),
+            other => Err(format!("unknown overflow strategy \"{}\"", other)),
+        }
+    }
+}
+
+/// Picks the trains a ring shows in split mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RingFilter {
+    Direction(Direction),
+    Platform(u8),
+}
+
+impl RingFilter {
+    pub fn matches(&self, departure: &Departure) -> bool {
+        //A train that could be going either way belongs on both rings
+        if departure.direction == Direction::Unknown {
+            return true;
+        }
+        match self {
+            RingFilter::Direction(direction) => departure.direction == *direction,
+            RingFilter::Platform(platform) => departure.platform == *platform,
+        }
+    }
+}
+
+/// Parses the config file form of a filter: `"north"`, `"south"`, `"inbound"`, `"outbound"` or `"platform:2"`.
+impl FromStr for RingFilter {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim().to_ascii_lowercase();
+        if let Some(platform) = value.strip_prefix("platform:") {
+            return platform.trim().parse().map(RingFilter::Platform).map_err(|_| format!("unknown platform \"{}\"", platform));
+        }
+        match value.as_str() {
+            "north" => Ok(RingFilter::Direction(Direction::North)),
+            "south" => Ok(RingFilter::Direction(Direction::South)),
+            "inbound" => Ok(RingFilter::Direction(Direction::Inbound)),
+            "outbound" => Ok(RingFilter::Direction(Direction::Outbound)),
+            _ => Err(format!("unknown ring filter \"{}\"", value)),
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use anyhow::Result;
 
-pub fn json_with_etd_3_trains(first: &str, second:&str) -> Result<String> {
+pub fn json_with_etd_3_trains(first: &str, second:&str) -> Result<Vec<u8>> {
     Ok(format!(r##"{{
 "?xml": {{
 "@version": "1.0",
@@ -152,11 +152,11 @@ pub fn json_with_etd_3_trains(first: &str, second:&str) -> Result<String> {
 ],
 "message": ""
 }}
-}}"##, first, second))
+}}"##, first, second).into_bytes())
 }
 
 
-pub fn json_with_etd_2_trains(first: &str, second:&str) -> Result<String> {
+pub fn json_with_etd_2_trains(first: &str, second:&str) -> Result<Vec<u8>> {
     Ok(format!(r##"{{
 "?xml": {{
 "@version": "1.0",
@@ -296,11 +296,11 @@ pub fn json_with_etd_2_trains(first: &str, second:&str) -> Result<String> {
 ],
 "message": ""
 }}
-}}"##, first, second))
+}}"##, first, second).into_bytes())
 }
 
 
-pub fn json_with_etd_1_train(first: &str) -> Result<String> {
+pub fn json_with_etd_1_train(first: &str) -> Result<Vec<u8>> {
     Ok(format!(r##"{{
 "?xml": {{
 "@version": "1.0",
@@ -428,14 +428,271 @@ pub fn json_with_etd_1_train(first: &str) -> Result<String> {
 ],
 "message": ""
 }}
-}}"##, first))
+}}"##, first).into_bytes())
 }
 
 
+pub fn json_no_service() -> Result<Vec<u8>> {
+    Ok(r##"{
+"?xml": {
+"@version": "1.0",
+"@encoding": "utf-8"
+},
+"root": {
+"@id": "1",
+"uri": {
+"#cdata-section": "http://api.bart.gov/api/etd.aspx?cmd=etd&orig=ROCK&json=y"
+},
+"date": "05/10/2024",
+"time": "02:12:44 AM PDT",
+"station": [
+{
+"name": "Rockridge",
+"abbr": "ROCK"
+}
+],
+"message": {
+"warning": "No data matched your criteria."
+}
+}
+}"##.as_bytes().to_vec())
+}
+
+pub fn json_api_error(text: &str, details: &str) -> Result<Vec<u8>> {
+    Ok(format!(r##"{{
+"?xml": {{
+"@version": "1.0",
+"@encoding": "utf-8"
+}},
+"root": {{
+"@id": "1",
+"uri": {{
+"#cdata-section": "http://api.bart.gov/api/etd.aspx?cmd=etd&orig=ROCK&json=y"
+}},
+"message": {{
+"error": {{
+"text": "{}",
+"details": "{}"
+}}
+}}
+}}
+}}"##, text, details).into_bytes())
+}
+
+pub fn json_station_schedule(items: &[(&str, &str)]) -> Result<Vec<u8>> {
+    let items: Vec<String> = items.iter()
+        .enumerate()
+        .map(|(index, (head_station, orig_time))| format!(r##"{{
+"@line": "ROUTE 1",
+"@trainHeadStation": "{}",
+"@origTime": "{}",
+"@destTime": "6:17 AM",
+"@trainIdx": "{}",
+"@bikeflag": "1",
+"@trainId": "{}",
+"@load": "0"
+}}"##, head_station, orig_time, index + 1, index + 1))
+        .collect();
+    Ok(format!(r##"{{
+"?xml": {{
+"@version": "1.0",
+"@encoding": "utf-8"
+}},
+"root": {{
+"uri": {{
+"#cdata-section": "http://api.bart.gov/api/sched.aspx?cmd=stnsched&orig=ROCK&json=y"
+}},
+"date": "07/03/2024",
+"station": {{
+"name": "Rockridge",
+"abbr": "ROCK",
+"item": [
+{}
+]
+}},
+"message": ""
+}}
+}}"##, items.join(",\n")).into_bytes())
+}
+
+pub fn json_station_schedule_single_item() -> Result<Vec<u8>> {
+    Ok(r##"{
+"root": {
+"date": "07/03/2024",
+"station": {
+"name": "Rockridge",
+"abbr": "ROCK",
+"item": {
+"@line": "ROUTE 1",
+"@trainHeadStation": "MLBR",
+"@origTime": "11:58 PM",
+"@destTime": "12:41 AM",
+"@trainIdx": "1",
+"@bikeflag": "0",
+"@trainId": "1",
+"@load": "0"
+}
+},
+"message": ""
+}
+}"##.as_bytes().to_vec())
+}
+
+pub fn json_advisories(station: &str, description: &str) -> Result<Vec<u8>> {
+    Ok(format!(r##"{{
+"?xml": {{
+"@version": "1.0",
+"@encoding": "utf-8"
+}},
+"root": {{
+"@id": "1",
+"uri": {{
+"#cdata-section": "http://api.bart.gov/api/bsa.aspx?cmd=bsa&orig=ROCK&json=y"
+}},
+"date": "05/10/2024",
+"time": "08:52:00 AM PDT",
+"bsa": [
+{{
+"@id": "267913",
+"station": "{}",
+"type": "DELAY",
+"description": {{
+"#cdata-section": "{}"
+}},
+"sms_text": {{
+"#cdata-section": "10-min delay at Lake Merritt."
+}},
+"posted": "Fri May 10 2024 08:44 AM PDT",
+"expires": "Thu Dec 31 2037 11:59 PM PST"
+}}
+],
+"message": ""
+}}
+}}"##, station, description).into_bytes())
+}
 
+pub fn json_no_advisories() -> Result<Vec<u8>> {
+    Ok(r##"{
+"?xml": {
+"@version": "1.0",
+"@encoding": "utf-8"
+},
+"root": {
+"@id": "1",
+"uri": {
+"#cdata-section": "http://api.bart.gov/api/bsa.aspx?cmd=bsa&orig=ROCK&json=y"
+},
+"date": "05/10/2024",
+"time": "09:31:00 AM PDT",
+"bsa": [
+{
+"station": "",
+"description": {
+"#cdata-section": "No delays reported."
+},
+"sms_text": {
+"#cdata-section": "No delays reported."
+}
+}
+],
+"message": ""
+}
+}"##.as_bytes().to_vec())
+}
 
+pub fn json_elevators(description: &str) -> Result<Vec<u8>> {
+    Ok(format!(r##"{{
+"?xml": {{
+"@version": "1.0",
+"@encoding": "utf-8"
+}},
+"root": {{
+"@id": "1",
+"uri": {{
+"#cdata-section": "http://api.bart.gov/api/bsa.aspx?cmd=elev&json=y"
+}},
+"date": "05/10/2024",
+"time": "09:35:00 AM PDT",
+"bsa": [
+{{
+"@id": "",
+"station": "BART",
+"type": "ELEVATOR",
+"description": {{
+"#cdata-section": "{}"
+}},
+"sms_text": {{
+"#cdata-section": "Out of svc elevators"
+}},
+"posted": "",
+"expires": ""
+}}
+],
+"message": ""
+}}
+}}"##, description).into_bytes())
+}
 
+/// One train in an etd list, defaults to an on time 10 car train that takes bikes
+pub struct Estimate<'a> {
+    pub minutes: &'a str,
+    pub delay: &'a str,
+    pub cancelflag: &'a str,
+    pub length: &'a str,
+    pub bikeflag: &'a str,
+}
 
+impl<'a> Estimate<'a> {
+    pub fn new(minutes: &'a str) -> Self {
+        Self { minutes, delay: "0", cancelflag: "0", length: "10", bikeflag: "1" }
+    }
+}
 
+/// SF Airport trains on the yellow line
+pub fn json_with_estimates(estimates: &[Estimate]) -> Result<Vec<u8>> {
+    json_with_line_estimates(("SF Airport", "SFIA"), ("YELLOW", "#ffff33"), estimates)
+}
 
+/// Millbrae trains on the red line
+pub fn json_with_red_line_estimates(estimates: &[Estimate]) -> Result<Vec<u8>> {
+    json_with_line_estimates(("Millbrae", "MLBR"), ("RED", "#ff0000"), estimates)
+}
 
+fn json_with_line_estimates(destination: (&str, &str), color: (&str, &str), estimates: &[Estimate]) -> Result<Vec<u8>> {
+    let estimates: Vec<String> = estimates.iter().map(|estimate| format!(r##"{{
+"minutes": "{}",
+"platform": "2",
+"direction": "South",
+"length": "{}",
+"color": "{}",
+"hexcolor": "{}",
+"bikeflag": "{}",
+"delay": "{}",
+"cancelflag": "{}",
+"dynamicflag": "0"
+}}"##, estimate.minutes, estimate.length, color.0, color.1, estimate.bikeflag, estimate.delay, estimate.cancelflag)).collect();
+    Ok(format!(r##"{{
+"root": {{
+"@id": "1",
+"date": "05/09/2024",
+"time": "04:24:03 PM PDT",
+"station": [
+{{
+"name": "Rockridge",
+"abbr": "ROCK",
+"etd": [
+{{
+"destination": "{}",
+"abbreviation": "{}",
+"limited": "0",
+"estimate": [
+{}
+]
+}}
+]
+}}
+],
+"message": ""
+}}
+}}"##, destination.0, destination.1, estimates.join(",\n")).into_bytes())
+}
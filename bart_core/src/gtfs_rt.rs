@@ -0,0 +1,162 @@
+use prost::Message;
+
+use crate::{ApiError, Departure, DepartureState, Direction, Duration, FeedSnapshot, TransitProvider};
+#[cfg(test)]
+#[path = "gtfs_rt.test.rs"]
+mod tests;
+
+/// Departures from a GTFS-Realtime TripUpdates feed for a set of stops (and optionally routes).
+pub struct GtfsRtProvider {
+    feed_url: String,
+    stop_ids: Vec<String>,
+    route_ids: Vec<String>,
+}
+
+impl GtfsRtProvider {
+    /// An empty `route_ids` shows every route serving the stops.
+    pub fn new(feed_url: &str, stop_ids: Vec<String>, route_ids: Vec<String>) -> GtfsRtProvider {
+        GtfsRtProvider { feed_url: feed_url.to_string(), stop_ids, route_ids }
+    }
+
+    fn departure(&self, trip: &TripDescriptor, stop_time: &StopTimeUpdate, feed_time: i64) -> Option<Departure> {
+        if !self.stop_ids.iter().any(|stop_id| Some(stop_id.as_str()) == stop_time.stop_id.as_deref()) {
+            return None;
+        }
+        let route_id = trip.route_id.clone().unwrap_or_default();
+        if !self.route_ids.is_empty() && !self.route_ids.contains(&route_id) {
+            return None;
+        }
+        //A board cares when the vehicle leaves, the arrival is only a fallback for feeds that don't give one
+        let event = stop_time.departure.as_ref().or(stop_time.arrival.as_ref())?;
+        let seconds_until = event.time? - feed_time;
+        let cancelled = trip.schedule_relationship == Some(TRIP_CANCELED)
+            || stop_time.schedule_relationship == Some(STOP_SKIPPED);
+        let state = if cancelled {
+            DepartureState::Cancelled
+        } else if seconds_until < -SECONDS_PER_MIN {
+            //Already gone by the time the feed was built
+            return None;
+        } else if seconds_until < SECONDS_PER_MIN {
+            DepartureState::Leaving
+        } else {
+            DepartureState::Minutes((seconds_until / SECONDS_PER_MIN) as u32)
+        };
+        //direction_id 0 is conventionally outbound, 1 inbound, what that means is up to the agency
+        let direction = match trip.direction_id {
+            Some(0) => Direction::Outbound,
+            Some(1) => Direction::Inbound,
+            _ => Direction::Unknown,
+        };
+        Some(Departure {
+            destination: route_id.clone(),
+            abbreviation: route_id,
+            state,
+            platform: 0,
+            direction,
+            car_count: 0,
+            color: String::new(),
+            hexcolor: None,
+            bike_allowed: false,
+            delay: Duration::from_secs(event.delay.unwrap_or(0).max(0) as u64),
+        })
+    }
+}
+
+const SECONDS_PER_MIN: i64 = 60;
+//TripDescriptor.ScheduleRelationship.CANCELED and StopTimeUpdate.ScheduleRelationship.SKIPPED
+const TRIP_CANCELED: i32 = 3;
+const STOP_SKIPPED: i32 = 1;
+
+impl TransitProvider for GtfsRtProvider {
+    fn departures_url(&self) -> String {
+        self.feed_url.clone()
+    }
+
+    fn parse_departures(&self, payload: &[u8]) -> Result<FeedSnapshot, ApiError> {
+        let feed = FeedMessage::decode(payload)?;
+        //Minutes are counted from when the feed was built, the same way BART's minutes are
+        let feed_time = feed.header.timestamp
+            .ok_or_else(|| ApiError::Schema("feed header has no timestamp".to_string()))? as i64;
+        let departures = feed.entity
+            .iter()
+            .filter_map(|entity| entity.trip_update.as_ref())
+            .flat_map(|trip_update| {
+                trip_update.stop_time_update
+                    .iter()
+                    .filter_map(|stop_time| self.departure(&trip_update.trip, stop_time, feed_time))
+            })
+            .collect();
+        Ok(FeedSnapshot { departures, notices: Vec::new(), response_unix_sec: Some(feed_time) })
+    }
+}
+
+impl From<prost::DecodeError> for ApiError {
+    fn from(error: prost::DecodeError) -> Self {
+        ApiError::Schema(error.to_string())
+    }
+}
+
+//The subset of gtfs-realtime.proto we need, field tags have to match the spec
+#[derive(Clone, PartialEq, Message)]
+struct FeedMessage {
+    #[prost(message, required, tag = "1")]
+    header: FeedHeader,
+    #[prost(message, repeated, tag = "2")]
+    entity: Vec<FeedEntity>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct FeedHeader {
+    #[prost(string, required, tag = "1")]
+    gtfs_realtime_version: String,
+    #[prost(uint64, optional, tag = "3")]
+    timestamp: Option<u64>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct FeedEntity {
+    #[prost(string, required, tag = "1")]
+    id: String,
+    #[prost(message, optional, tag = "3")]
+    trip_update: Option<TripUpdate>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct TripUpdate {
+    #[prost(message, required, tag = "1")]
+    trip: TripDescriptor,
+    #[prost(message, repeated, tag = "2")]
+    stop_time_update: Vec<StopTimeUpdate>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct TripDescriptor {
+    #[prost(string, optional, tag = "1")]
+    trip_id: Option<String>,
+    #[prost(int32, optional, tag = "4")]
+    schedule_relationship: Option<i32>,
+    #[prost(string, optional, tag = "5")]
+    route_id: Option<String>,
+    #[prost(uint32, optional, tag = "6")]
+    direction_id: Option<u32>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct StopTimeUpdate {
+    #[prost(message, optional, tag = "2")]
+    arrival: Option<StopTimeEvent>,
+    #[prost(message, optional, tag = "3")]
+    departure: Option<StopTimeEvent>,
+    #[prost(string, optional, tag = "4")]
+    stop_id: Option<String>,
+    #[prost(int32, optional, tag = "5")]
+    schedule_relationship: Option<i32>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct StopTimeEvent {
+    #[prost(int32, optional, tag = "1")]
+    delay: Option<i32>,
+    #[prost(int64, optional, tag = "2")]
+    time: Option<i64>,
+}
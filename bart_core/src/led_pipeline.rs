@@ -1,7 +1,11 @@
 use std::f32::consts;
+use std::str::FromStr;
 
 use smart_leds::RGB8;
 use smart_leds::colors;
+
+use crate::{Departure, DepartureState, DisplayConfig, DisplayMode, LocalTime, OverflowStrategy, RingFilter};
+use crate::palette::CANCELLED_COLOR;
 pub trait PipelineStep {
     fn render(&self, led_buffer: &mut LEDBuffer, current_time_microsec: u64);
 }
@@ -39,53 +43,333 @@ impl LEDBuffer {
     fn center_ring(&mut self) -> &mut [RGB8] {
         &mut self.rgb_buffer[(Self::OUTSIDE_RING_SIZE + Self::INSIDE_RING_SIZE) as usize..]
     }
+}
+/// How many LEDs a countdown lights, the same scale on both rings.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TimeScale {
+    /// One minute per LED
+    #[default]
+    Linear,
+    /// Several minutes per LED, for stations with long headways
+    MinutesPerLed(u8),
+    /// Full detail for the next few minutes, compressed further out so an hour fills the ring
+    Log,
+}
+
+impl TimeScale {
+    const LOG_FULL_RING_MIN: f32 = 60.0;
+
+    /// LEDs lit for `minutes` on a ring of `ring_size`, the fraction is how bright the last LED is
+    pub fn leds(&self, minutes: f32, ring_size: i32) -> f32 {
+        let minutes = minutes.max(0.0);
+        match self {
+            TimeScale::Linear => minutes,
+            TimeScale::MinutesPerLed(per_led) => minutes / (*per_led).max(1) as f32,
+            TimeScale::Log => ring_size as f32 * (1.0 + minutes).ln() / (1.0 + Self::LOG_FULL_RING_MIN).ln(),
+        }
+    }
+}
 
-    fn fill_ring(ring: &mut [RGB8], count: i32, value: RGB8) {
-        let count = count as usize;
-        for led in ring.iter_mut().take(count) {
-            *led = value;
+/// Parses the config file form, `"linear"`, `"log"` or minutes per LED like `"2"`.
+impl FromStr for TimeScale {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "linear" => Ok(TimeScale::Linear),
+            "log" => Ok(TimeScale::Log),
+            other => match other.parse() {
+                Ok(1) => Ok(TimeScale::Linear),
+                Ok(per_led) if per_led > 1 => Ok(TimeScale::MinutesPerLed(per_led)),
+                _ => Err(format!("unknown time scale \"{}\"", other)),
+            },
         }
     }
 }
+
+/// One train as drawn on a ring
+#[derive(Debug, Clone, Copy, Default)]
+struct RingTrain {
+    minutes: f32,
+    delay_min: i32,
+    color: RGB8,
+    bike_marker: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ring {
+    Inside,
+    Outside,
+}
+
+/// What the outer ring shows besides a single countdown
+#[derive(Debug, Clone, Default)]
+enum OutsideRingView {
+    #[default]
+    Countdown,
+    Timeline(Vec<RingTrain>),
+    Headways(Vec<RingTrain>),
+}
+
 pub struct ETDLEDs {
-    inside_ring_count: i32,
-    outside_ring_count: i32,
+    inside_ring: RingTrain,
+    outside_ring: RingTrain,
+    /// Color of the train that's leaving right now, blinking on the first LED of the ring
+    inside_ring_leaving: Option<RGB8>,
+    outside_ring_leaving: Option<RGB8>,
+    tint: Option<RGB8>,
+    overflow: OverflowStrategy,
+    time_scale: TimeScale,
+    leave_in: Option<(Ring, f32)>,
+    outside_ring_view: OutsideRingView,
 }
 
 impl ETDLEDs {
+    const LEAVING_BLINK_MICROSEC: u64 = 500000;
+    const HEAVY_DELAY_MIN: i32 = 10;
+    const FULL_LENGTH_CAR_COUNT: u8 = 10;
+    const SHORT_TRAIN_BRIGHTNESS: u8 = 127;
+    const SECOND_LAP_COLOR: RGB8 = colors::PURPLE;
+    const BUNCHED_TICK_COLOR: RGB8 = colors::WHITE;
+    const LATER_TICK_BRIGHTNESS: u8 = 63;
+    const ALTERNATE_GAP_BRIGHTNESS: u8 = 63;
     pub fn new() -> ETDLEDs {
-        ETDLEDs { inside_ring_count: 0, outside_ring_count: 0 }
+        ETDLEDs { inside_ring: RingTrain::default(), outside_ring: RingTrain::default(), inside_ring_leaving: None, outside_ring_leaving: None, tint: None, overflow: OverflowStrategy::default(), time_scale: TimeScale::default(), leave_in: None, outside_ring_view: OutsideRingView::default() }
     }
-    pub fn update(&mut self, etd_mins: &Vec<i32>, elapsed_since_fetch_microsec: u64) {
+
+    /// Draws every train in `color` instead of its route color, e.g. for times that come from a timetable
+    pub fn tinted(color: RGB8) -> ETDLEDs {
+        ETDLEDs { tint: Some(color), ..Self::new() }
+    }
+
+    pub fn update(&mut self, departures: &[Departure], elapsed_since_fetch_microsec: u64, display_config: &DisplayConfig) {
         const MICROSEC_PER_MIN: u64 = 60000000;
-        let elapse_time_min = i32::try_from(elapsed_since_fetch_microsec/MICROSEC_PER_MIN).unwrap();
-        let current_etd_min: Vec<i32> = etd_mins.iter()
-            .map(|etd| etd - elapse_time_min)//subtract time since fetch
-            .filter(|etd| *etd > 0i32)//Filter out trains which have already left
+        const SEC_PER_MIN: u64 = 60;
+        //Fractional so the last LED fades out over the minute instead of going dark all at once
+        let elapse_time_min = elapsed_since_fetch_microsec as f32 / MICROSEC_PER_MIN as f32;
+        let color_for = |departure: &Departure| {
+            let color = self.tint.unwrap_or_else(|| display_config.palette.color_for(departure));
+            //Zero cars means the feed doesn't know
+            let short_train = departure.car_count != 0 && departure.car_count < Self::FULL_LENGTH_CAR_COUNT;
+            if display_config.train_details && short_train {
+                color.dim(Self::SHORT_TRAIN_BRIGHTNESS)
+            } else {
+                color
+            }
+        };
+        let walk_min = display_config.walk_time.walk_min as f32;
+        let run_min = display_config.walk_time.run_min as f32;
+        let (current_etd_min, run_now): (Vec<(&Departure, RingTrain)>, Vec<_>) = departures.iter()
+            .filter_map(|departure| match departure.state {
+                DepartureState::Minutes(minutes) => Some((departure, RingTrain {
+                    minutes: minutes as f32 - elapse_time_min - walk_min,//subtract time since fetch, count down to when to leave
+                    delay_min: (departure.delay.as_secs() / SEC_PER_MIN) as i32,
+                    color: color_for(departure),
+                    bike_marker: display_config.train_details && departure.bike_allowed,
+                })),
+                _ => None
+            })
+            .filter(|(_, train)| train.minutes > -run_min.min(walk_min))//Filter out trains which have already left or can't be caught any more
+            .partition(|(_, train)| train.minutes > 0.0);
+        //A leaving train is only news until the first minute after the fetch is up, and only when the platform is right here.
+        //A train that's only catchable by running blinks the same way, and keeps its minutes for the urgency pulse
+        let leaving: Vec<(&Departure, Option<f32>)> = departures.iter()
+            .filter(|departure| departure.state == DepartureState::Leaving && elapsed_since_fetch_microsec < MICROSEC_PER_MIN && walk_min == 0.0)
+            .map(|departure| (departure, None))
+            .chain(run_now.iter().map(|(departure, train)| (*departure, Some(train.minutes))))
             .collect();
-        self.outside_ring_count = 0;
-        self.inside_ring_count = 0;
-        if current_etd_min.is_empty() {
-            return;
-        }
-        let next_train =  current_etd_min[0];
-        if next_train > LEDBuffer::INSIDE_RING_SIZE {
-            self.outside_ring_count = next_train;
-        } else {
-            self.inside_ring_count = next_train;
-            if current_etd_min.len() >= 2 {
-                let next_next_train = current_etd_min[1];
-                self.outside_ring_count = next_next_train;
+        self.outside_ring = RingTrain::default();
+        self.inside_ring = RingTrain::default();
+        self.inside_ring_leaving = None;
+        self.outside_ring_leaving = None;
+        self.outside_ring_view = OutsideRingView::Countdown;
+        self.overflow = display_config.overflow;
+        self.time_scale = display_config.time_scale;
+        //Minutes until leaving for the train blinking on each ring, only there when it takes running
+        let (inside_leaving_min, outside_leaving_min) = match &display_config.mode {
+            DisplayMode::NextTwo => {
+                self.inside_ring_leaving = leaving.first().map(|(departure, _)| color_for(departure));
+                if let Some((_, next_train)) = current_etd_min.first() {
+                    let fits_inside = self.time_scale.leds(next_train.minutes, LEDBuffer::INSIDE_RING_SIZE) <= LEDBuffer::INSIDE_RING_SIZE as f32;
+                    if !fits_inside || self.inside_ring_leaving.is_some() {
+                        self.outside_ring = *next_train;
+                    } else {
+                        self.inside_ring = *next_train;
+                        if let Some((_, second_train)) = current_etd_min.get(1) {
+                            self.outside_ring = *second_train;
+                        }
+                    }
+                }
+                (leaving.first().and_then(|(_, minutes)| *minutes), None)
+            }
+            DisplayMode::Split { inner, outer } => {
+                //Each ring counts down to its own next train and shows its own leaving train
+                let next_matching = |filter: &RingFilter| {
+                    current_etd_min.iter()
+                        .find(|(departure, _)| filter.matches(departure))
+                        .map(|(_, train)| *train)
+                        .unwrap_or_default()
+                };
+                let leaving_matching = |filter: &RingFilter| {
+                    leaving.iter().find(|(departure, _)| filter.matches(departure))
+                };
+                self.inside_ring = next_matching(inner);
+                self.outside_ring = next_matching(outer);
+                self.inside_ring_leaving = leaving_matching(inner).map(|(departure, _)| color_for(departure));
+                self.outside_ring_leaving = leaving_matching(outer).map(|(departure, _)| color_for(departure));
+                (leaving_matching(inner).and_then(|(_, minutes)| *minutes), leaving_matching(outer).and_then(|(_, minutes)| *minutes))
+            }
+            DisplayMode::Timeline | DisplayMode::Headway => {
+                self.inside_ring_leaving = leaving.first().map(|(departure, _)| color_for(departure));
+                self.inside_ring = current_etd_min.first().map(|(_, train)| *train).unwrap_or_default();
+                let trains = current_etd_min.iter().map(|(_, train)| *train).collect();
+                self.outside_ring_view = if display_config.mode == DisplayMode::Timeline {
+                    OutsideRingView::Timeline(trains)
+                } else {
+                    OutsideRingView::Headways(trains)
+                };
+                (leaving.first().and_then(|(_, minutes)| *minutes), None)
+            }
+        };
+        //Otherwise each ring's countdown, an empty ring has no minutes. The outer ring only has its own countdown in Countdown view,
+        //otherwise its first train is the inner ring's
+        let countdown = |train: &RingTrain| Some(train.minutes).filter(|minutes| *minutes > 0.0);
+        let inside_leave_in_min = inside_leaving_min.or(countdown(&self.inside_ring));
+        let outside_leave_in_min = outside_leaving_min.or_else(|| match self.outside_ring_view {
+            OutsideRingView::Countdown => countdown(&self.outside_ring),
+            _ => None,
+        });
+        self.leave_in = match (inside_leave_in_min, outside_leave_in_min) {
+            (Some(inside), Some(outside)) if outside < inside => Some((Ring::Outside, outside)),
+            (None, Some(outside)) => Some((Ring::Outside, outside)),
+            (inside, _) => inside.map(|inside| (Ring::Inside, inside)),
+        };
+    }
+
+    /// The ring showing the soonest catchable train and the minutes until leaving for it, zero or less once it takes running
+    pub fn leave_in(&self) -> Option<(Ring, f32)> {
+        self.leave_in
+    }
+
+    /// Whether anything will be drawn on the rings. Cancelled, departed and filtered out trains don't count
+    pub fn shows_trains(&self) -> bool {
+        let outside_ring_trains = match &self.outside_ring_view {
+            OutsideRingView::Countdown => self.outside_ring.minutes > 0.0,
+            OutsideRingView::Timeline(trains) | OutsideRingView::Headways(trains) => !trains.is_empty(),
+        };
+        outside_ring_trains
+            || self.inside_ring.minutes > 0.0
+            || self.inside_ring_leaving.is_some()
+            || self.outside_ring_leaving.is_some()
+    }
+
+    fn render_timeline(&self, ring: &mut [RGB8], trains: &[RingTrain]) {
+        let ring_size = ring.len() as i32;
+        let mut ticks = vec![0; ring.len()];
+        let mut later = None;
+        for train in trains {
+            //A train due in 4 min ticks the LED a 4 min countdown would end on
+            let position = (self.time_scale.leds(train.minutes, ring_size).ceil() as i32 - 1).max(0);
+            if position >= ring_size {
+                later = later.or(Some(train.color));
+                continue;
             }
+            let position = position as usize;
+            ticks[position] += 1;
+            //Trains in the same minute can't share a route color, white says there's more than one
+            ring[position] = if ticks[position] > 1 { Self::BUNCHED_TICK_COLOR } else { train.color };
+        }
+        //A dim last LED says there are trains past the end of the timeline
+        let last = ring.len() - 1;
+        if let Some(color) = later.filter(|_| ticks[last] == 0) {
+            ring[last] = color.dim(Self::LATER_TICK_BRIGHTNESS);
+        }
+    }
+
+    fn render_headways(&self, ring: &mut [RGB8], trains: &[RingTrain]) {
+        let ring_size = ring.len() as i32;
+        let mut gap_total_min = 0.0;
+        let mut start = 0;
+        let mut bunched = 0;
+        let mut segment = 0;
+        for pair in trains.windows(2) {
+            if start >= ring_size {
+                break;
+            }
+            let gap_min = pair[1].minutes - pair[0].minutes;
+            //Trains in the same minute have no gap to draw, a white LED says they're bunched
+            if gap_min < 1.0 {
+                ring[start as usize] = Self::BUNCHED_TICK_COLOR;
+                start += 1;
+                bunched += 1;
+                continue;
+            }
+            gap_total_min += gap_min;
+            let end = (self.time_scale.leds(gap_total_min, ring_size).round() as i32 + bunched).min(ring_size);
+            //Alternate brightness so neighboring gaps with the same route color can be told apart
+            let color = if segment & 1 == 0 { pair[1].color } else { pair[1].color.dim(Self::ALTERNATE_GAP_BRIGHTNESS) };
+            for led in ring.iter_mut().take(end.max(0) as usize).skip(start as usize) {
+                *led = color;
+            }
+            start = end.max(start);
+            segment += 1;
+        }
+    }
+
+    fn render_train(&self, ring: &mut [RGB8], train: &RingTrain) {
+        let ring_size = ring.len() as i32;
+        let laps = match self.overflow {
+            OverflowStrategy::Truncate => 1,
+            OverflowStrategy::Wrap => 2,
+        };
+        let led_limit = ring_size * laps;
+        let lit = self.time_scale.leds(train.minutes, ring_size);
+        //Recolor the LEDs past the minute the train was scheduled to leave at, amber when late and orange-red when very late
+        let delay_color = if train.delay_min >= Self::HEAVY_DELAY_MIN { colors::ORANGE_RED } else { colors::ORANGE };
+        let on_time_lit = if train.delay_min > 0 {
+            self.time_scale.leds(train.minutes - train.delay_min as f32, ring_size).round() as i32
+        } else {
+            i32::MAX
+        };
+        //LEDs past the end of the ring start over from the first LED, drawn over the lap before them
+        let lit_leds = (lit.ceil() as i32).min(led_limit);
+        for led in 0..lit_leds {
+            let color = if led >= on_time_lit {
+                delay_color
+            } else if led >= ring_size {
+                Self::SECOND_LAP_COLOR
+            } else {
+                train.color
+            };
+            //The last LED is only as bright as the part of it the countdown covers
+            let coverage = (lit - led as f32).min(1.0);
+            let color = if coverage < 1.0 { color.dim((coverage * 255.0) as u8) } else { color };
+            ring[(led % ring_size) as usize] = color;
+        }
+        if train.bike_marker && lit > 0.0 && lit_leds < led_limit {
+            ring[(lit_leds % ring_size) as usize] = colors::GREEN;
         }
     }
 }
 
 impl PipelineStep for ETDLEDs {
-    fn render(&self, led_buffer: &mut LEDBuffer, _current_time_microsec: u64) {
-        let color = colors::WHITE;
-        LEDBuffer::fill_ring(led_buffer.inside_ring(), self.inside_ring_count, color);
-        LEDBuffer::fill_ring(led_buffer.outside_ring(), self.outside_ring_count, color);
+    fn render(&self, led_buffer: &mut LEDBuffer, current_time_microsec: u64) {
+        self.render_train(led_buffer.inside_ring(), &self.inside_ring);
+        match &self.outside_ring_view {
+            OutsideRingView::Countdown => self.render_train(led_buffer.outside_ring(), &self.outside_ring),
+            OutsideRingView::Timeline(trains) => self.render_timeline(led_buffer.outside_ring(), trains),
+            OutsideRingView::Headways(trains) => self.render_headways(led_buffer.outside_ring(), trains),
+        }
+        //Blink the first LED so a just missed train doesn't look like a broken display.
+        //It goes dark in between even when the ring's next train would light it
+        let blink_on = (current_time_microsec / Self::LEAVING_BLINK_MICROSEC) & 1 == 0;
+        let blink = |color: RGB8| if blink_on { color } else { colors::BLACK };
+        if let Some(color) = self.inside_ring_leaving {
+            led_buffer.inside_ring()[0] = blink(color);
+        }
+        if let Some(color) = self.outside_ring_leaving {
+            led_buffer.outside_ring()[0] = blink(color);
+        }
     }
 }
 
@@ -118,6 +402,176 @@ impl PipelineStep for NetworkAnimation {
     }
 }
 
+pub struct UrgencyAnimation {
+    ring: Ring,
+    pulse_microsec: u64
+}
+
+impl UrgencyAnimation {
+    const PULSE_STEP_MICROSEC: u64 = 250000;
+    const DIMMEST: f32 = 0.25;
+    /// Pulses faster every minute closer to leaving, four times a second once it takes running
+    pub fn new(ring: Ring, leave_in_min: f32) -> Self {
+        let minutes_left = leave_in_min.ceil().max(0.0) as u64;
+        Self { ring, pulse_microsec: Self::PULSE_STEP_MICROSEC * (minutes_left + 1) }
+    }
+}
+
+impl PipelineStep for UrgencyAnimation {
+    fn render(&self, led_buffer: &mut LEDBuffer, current_time_microsec: u64) {
+        //Breathe the countdown of the ring with the train to catch between full and a quarter brightness
+        let phase = (current_time_microsec % self.pulse_microsec) as f32 / self.pulse_microsec as f32;
+        let wave = ((phase * 2.0 * consts::PI).cos() + 1.0) / 2.0; //1 - 0 - 1
+        let brightness = Self::DIMMEST + wave * (1.0 - Self::DIMMEST);
+        let ring = match self.ring {
+            Ring::Inside => led_buffer.inside_ring(),
+            Ring::Outside => led_buffer.outside_ring(),
+        };
+        for led in ring.iter_mut() {
+            *led = led.dim((brightness * 255.0) as u8);
+        }
+    }
+}
+
+pub struct ClockFace {
+    hour: u32,
+    minute: u32,
+}
+
+impl ClockFace {
+    const MINUTES_PER_HALF_DAY: u32 = 720;
+    pub fn new(local_time: LocalTime) -> Self {
+        Self { hour: local_time.hour, minute: local_time.minute }
+    }
+}
+
+impl PipelineStep for ClockFace {
+    fn render(&self, led_buffer: &mut LEDBuffer, _current_time_microsec: u64) {
+        //One LED per hand so it can't be mistaken for a countdown. The hour hand creeps along with the minutes like an analog clock,
+        //the minute hand moves every 2.5 minutes
+        let color = colors::WHITE;
+        let minute_of_half_day = (self.hour % 12) * 60 + self.minute;
+        let hour_led = minute_of_half_day * LEDBuffer::INSIDE_RING_SIZE as u32 / Self::MINUTES_PER_HALF_DAY;
+        let minute_led = self.minute * LEDBuffer::OUTSIDE_RING_SIZE as u32 / 60;
+        led_buffer.inside_ring()[hour_led as usize] = color;
+        led_buffer.outside_ring()[minute_led as usize] = color;
+    }
+}
+
+pub struct NoServiceIndicator {}
+
+impl NoServiceIndicator {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl PipelineStep for NoServiceIndicator {
+    fn render(&self, led_buffer: &mut LEDBuffer, _current_time_microsec: u64) {
+        //A steady dim blue center says "no trains right now" rather than "something is broken"
+        let color = colors::MIDNIGHT_BLUE;
+        for led in led_buffer.center_ring().iter_mut() {
+            *led = color;
+        }
+    }
+}
+
+pub struct ErrorIndicator {
+    configuration_error: bool
+}
+
+impl ErrorIndicator {
+    pub fn new(configuration_error: bool) -> Self {
+        Self { configuration_error }
+    }
+}
+
+impl PipelineStep for ErrorIndicator {
+    fn render(&self, led_buffer: &mut LEDBuffer, _current_time_microsec: u64) {
+        //A full red center means someone needs to fix the config, a single red LED means we'll retry on our own
+        let color = colors::RED;
+        let ring = led_buffer.center_ring();
+        if self.configuration_error {
+            for led in ring.iter_mut() {
+                *led = color;
+            }
+        } else {
+            ring[0] = color;
+        }
+    }
+}
+
+pub struct AdvisoryIndicator {}
+
+impl AdvisoryIndicator {
+    const BLINK_MICROSEC: u64 = 1000000;
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl PipelineStep for AdvisoryIndicator {
+    fn render(&self, led_buffer: &mut LEDBuffer, current_time_microsec: u64) {
+        //A slow amber blink on one LED, unlike the network animation's white pulse on all four
+        let blink_on = (current_time_microsec / Self::BLINK_MICROSEC) & 1 == 0;
+        if blink_on {
+            led_buffer.center_ring()[2] = colors::ORANGE;
+        }
+    }
+}
+
+pub struct CancelledIndicator {}
+
+impl CancelledIndicator {
+    const BLINK_MICROSEC: u64 = 250000;
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl PipelineStep for CancelledIndicator {
+    fn render(&self, led_buffer: &mut LEDBuffer, current_time_microsec: u64) {
+        //A fast flash, in its own color so it can't be mistaken for the red of an error
+        let blink_on = (current_time_microsec / Self::BLINK_MICROSEC) & 1 == 0;
+        if blink_on {
+            for led in led_buffer.center_ring().iter_mut() {
+                *led = CANCELLED_COLOR;
+            }
+        }
+    }
+}
+
+pub struct ElevatorMarker {}
+
+impl ElevatorMarker {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl PipelineStep for ElevatorMarker {
+    fn render(&self, led_buffer: &mut LEDBuffer, _current_time_microsec: u64) {
+        //Steady rather than blinking, it stays true until BART fixes the elevator
+        led_buffer.center_ring()[3] = colors::MAGENTA;
+    }
+}
+
+pub struct ScheduledMarker {}
+
+impl ScheduledMarker {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl PipelineStep for ScheduledMarker {
+    fn render(&self, led_buffer: &mut LEDBuffer, _current_time_microsec: u64) {
+        //A cyan LED says the rings come from the timetable, not live predictions.
+        //The other center LEDs are the error, advisory and elevator markers, which can all show at the same time
+        led_buffer.center_ring()[1] = colors::CYAN;
+    }
+}
+
 pub struct Dim {
     scale_value: u8
 }
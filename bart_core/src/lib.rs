@@ -1,29 +1,143 @@
 #![feature(duration_abs_diff)]
-use serde::Deserialize;
 use anyhow::Result;
+use smart_leds::{colors, RGB8};
 pub use core::time::Duration;
 #[cfg(test)]
 #[path = "lib.test.rs"]
 mod tests;
 mod led_pipeline;
-use led_pipeline::{Dim, ETDLEDs, LEDBuffer, NetworkAnimation, PipelineStep};
+mod config;
+mod departure;
+mod api;
+mod advisory;
+mod bart_advisory;
+mod bart_stations;
+mod provider;
+mod bart;
+mod bart_schedule;
+mod gtfs_rt;
+mod siri;
+mod time;
+mod palette;
+mod display;
+mod timetable;
+#[cfg(test)]
+mod static_gtfs;
+pub use advisory::{Advisory, ElevatorOutage};
+pub use api::{ApiError, ApiNotice};
+pub use bart::BartProvider;
+pub use gtfs_rt::GtfsRtProvider;
+pub use siri::SiriProvider;
+pub use provider::{FeedSnapshot, TransitProvider};
+pub use config::{DestinationFilter, StationConfig};
+pub use departure::{Departure, DepartureState, Direction};
+pub use display::{DisplayConfig, DisplayMode, OverflowStrategy, RingFilter, WalkTime};
+pub use palette::RoutePalette;
+pub use time::{LocalTime, TimeZone};
+pub use timetable::StaticTimetable;
+pub use led_pipeline::TimeScale;
+use led_pipeline::{AdvisoryIndicator, CancelledIndicator, ClockFace, Dim, ETDLEDs, ElevatorMarker, ErrorIndicator, LEDBuffer, NetworkAnimation, NoServiceIndicator, PipelineStep, ScheduledMarker, UrgencyAnimation};
 
 const FETCH_CORRECTION_TIME_MIN: i32 = 10;
 const FETCH_REFRESH_TIME_MIN: i32 = 5;
 const FETCH_NEXT_TRAIN_TIME_MIN: i32 = 2;
 const FETCH_RETRY_TIME_MIN: i32 = 2;
+const FETCH_NO_SERVICE_TIME_MIN: i32 = 15;
+const FETCH_SERVER_ERROR_TIME_MIN: i32 = 5;
+const FETCH_RATE_LIMITED_TIME_MIN: i32 = 15;
+const FETCH_CONFIGURATION_ERROR_TIME_MIN: i32 = 60;
+const FETCH_ADVISORIES_TIME_MIN: u64 = 15;
+const CANCELLED_INDICATOR_SEC: u64 = 30;
 const NETWORK_SLEEP_TIME_MIN: u64 = 10;
+const STATIC_FALLBACK_AFTER_MIN: u64 = 15;
+const STATIC_HORIZON_MIN: u32 = 60;
+const SCHEDULED_TINT: RGB8 = colors::DODGER_BLUE;
+//A response claiming to be older than this means the clocks disagree, not that the data is stale
+const MAX_RESPONSE_AGE_SEC: u64 = 120;
+const MICROSEC_PER_SEC: u64 = 1000000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceStatus {
+    Unknown,
+    Running,
+    NoService,
+}
 
 pub struct AppState {
-    etd_mins: Vec<i32>,
+    provider: Box<dyn TransitProvider>,
+    departures: Vec<Departure>,
+    notices: Vec<ApiNotice>,
+    service_status: ServiceStatus,
+    last_error: Option<ApiError>,
     last_fetch_time: u64,
+    /// How old the last response already was when it arrived, added to the time since the fetch
+    response_age_microsec: u64,
+    response_unix_sec: Option<i64>,
     network_animation: Option<NetworkAnimation>,
-    last_motion_sensed: Duration
+    last_motion_sensed: Duration,
+    static_timetable: StaticTimetable,
+    time_zone: TimeZone,
+    boot_unix_sec: Option<i64>,
+    last_live_data_time: u64,
+    first_scheduled: Option<Departure>,
+    schedule_fetch_time: u64,
+    advisories: Vec<Advisory>,
+    advisories_fetch_time: Option<u64>,
+    elevator_outages: Vec<ElevatorOutage>,
+    elevators_fetch_time: Option<u64>,
+    display_config: DisplayConfig
 }
 
 impl AppState {
-    pub fn new(now: Duration) -> AppState {
-        AppState {etd_mins: Vec::new(), last_fetch_time: 0, network_animation: None, last_motion_sensed: now}
+    /// Every departure comes from the one `provider`, both rings included. Split mode can separate its
+    /// departures by direction or platform but can't put a second agency on the other ring
+    pub fn new(now: Duration, provider: Box<dyn TransitProvider>) -> AppState {
+        AppState {provider, departures: Vec::new(), notices: Vec::new(), service_status: ServiceStatus::Unknown, last_error: None, last_fetch_time: 0, response_age_microsec: 0, response_unix_sec: None, network_animation: None, last_motion_sensed: now, static_timetable: StaticTimetable::compiled(), time_zone: TimeZone::pacific(), boot_unix_sec: None, last_live_data_time: 0, first_scheduled: None, schedule_fetch_time: 0, advisories: Vec::new(), advisories_fetch_time: None, elevator_outages: Vec::new(), elevators_fetch_time: None, display_config: DisplayConfig::default()}
+    }
+
+    pub fn set_static_timetable(&mut self, static_timetable: StaticTimetable) {
+        self.static_timetable = static_timetable;
+    }
+
+    pub fn set_display_config(&mut self, display_config: DisplayConfig) {
+        self.display_config = display_config;
+    }
+
+    pub fn display_mode(&self) -> &DisplayMode {
+        &self.display_config.mode
+    }
+
+    /// Switches what the rings show without touching the rest of the display config
+    pub fn set_display_mode(&mut self, mode: DisplayMode) {
+        self.display_config.mode = mode;
+    }
+
+    pub fn set_time_zone(&mut self, time_zone: TimeZone) {
+        self.time_zone = time_zone;
+    }
+
+    /// Called once SNTP has set the wall clock, `unix_time` is the time since the epoch at `current_time_microsec`
+    pub fn time_synced(&mut self, unix_time: Duration, current_time_microsec: u64) {
+        let since_boot_sec = (current_time_microsec / MICROSEC_PER_SEC) as i64;
+        self.boot_unix_sec = Some(unix_time.as_secs() as i64 - since_boot_sec);
+    }
+
+    pub fn local_time(&self, current_time_microsec: u64) -> Option<LocalTime> {
+        let boot_unix_sec = self.boot_unix_sec?;
+        let unix_sec = boot_unix_sec + (current_time_microsec / MICROSEC_PER_SEC) as i64;
+        Some(self.time_zone.local_time(unix_sec))
+    }
+
+    /// True when live data has been missing long enough that the rings show the compiled-in timetable
+    pub fn showing_static_timetable(&self, current_time_microsec: u64) -> bool {
+        let live_data_age_sec = current_time_microsec.saturating_sub(self.last_live_data_time) / MICROSEC_PER_SEC;
+        live_data_age_sec >= STATIC_FALLBACK_AFTER_MIN * 60
+            && self.boot_unix_sec.is_some()
+            && !self.static_timetable.is_empty()
+    }
+
+    pub fn departures_url(&self) -> String {
+        self.provider.departures_url()
     }
 
     pub fn network_activity_started(&mut self, elapse_time_microsec: u64) {
@@ -34,35 +148,156 @@ impl AppState {
         self.network_animation = None;
     }
 
-    pub fn received_http_response(&mut self, response: Result<String>, current_time_microsec: u64) -> u64 {
+    pub fn received_http_response(&mut self, response: Result<Vec<u8>>, current_time_microsec: u64) -> u64 {
         self.last_fetch_time = current_time_microsec;
         self.network_activity_complete();
-        let minutes = match response {
-            Err(error) => {
-                log::error!("Server Response Err {:?}", error);
-                FETCH_RETRY_TIME_MIN
+        let minutes = match self.process_response(response) {
+            Ok(()) => {
+                self.last_error = None;
+                self.last_live_data_time = current_time_microsec;
+                self.response_age_microsec = self.response_age_microsec(current_time_microsec);
+                self.next_fetch_time()
             }
-            Ok(payload) => {
-                match self.parse_json(payload) {
-                    Ok(json) => {
-                        log::info!("JSON: {:?}", json);
-                        self.update_state(json);
-                        self.next_fetch_time()
-                    },
-                    Err(error) => {
-                        log::error!("JSON: parse error {:?}", error);
-                        FETCH_RETRY_TIME_MIN
-                    }
-                } 
+            Err(error) => {
+                log::error!("Fetch failed: {}", error);
+                let minutes = Self::retry_time(&error);
+                self.last_error = Some(error);
+                minutes
             }
         };
         minutes as u64 * 60
     }
+
+    /// The timetable to fetch after the live feed came back empty, only while the wall clock is known
+    pub fn schedule_url(&self) -> Option<String> {
+        if self.service_status != ServiceStatus::NoService || self.boot_unix_sec.is_none() {
+            return None;
+        }
+        self.provider.schedule_url()
+    }
+
+    pub fn received_schedule_response(&mut self, response: Result<Vec<u8>>, current_time_microsec: u64) {
+        self.schedule_fetch_time = current_time_microsec;
+        let Some(local_time) = self.local_time(current_time_microsec) else {
+            return;
+        };
+        let scheduled = response
+            .map_err(ApiError::from)
+            .and_then(|payload| self.provider.parse_schedule(&payload, local_time));
+        self.first_scheduled = match scheduled {
+            Ok(scheduled) => scheduled.into_iter().next(),
+            Err(error) => {
+                log::error!("Schedule fetch failed: {}", error);
+                None
+            }
+        };
+    }
+
+    pub fn first_scheduled(&self) -> Option<&Departure> {
+        self.first_scheduled.as_ref()
+    }
+
+    /// The advisories to fetch alongside departures, `None` until the last fetch is old enough
+    pub fn advisories_url(&self, current_time_microsec: u64) -> Option<String> {
+        if !Self::advisory_fetch_due(self.advisories_fetch_time, current_time_microsec) {
+            return None;
+        }
+        self.provider.advisories_url()
+    }
+
+    pub fn received_advisories_response(&mut self, response: Result<Vec<u8>>, current_time_microsec: u64) {
+        self.advisories_fetch_time = Some(current_time_microsec);
+        let advisories = response
+            .map_err(ApiError::from)
+            .and_then(|payload| self.provider.parse_advisories(&payload));
+        match advisories {
+            Ok(advisories) => self.advisories = advisories,
+            //Keep showing what we knew, advisories rarely change within one fetch interval
+            Err(error) => log::error!("Advisory fetch failed: {}", error),
+        }
+    }
+
+    pub fn advisories(&self) -> &[Advisory] {
+        &self.advisories
+    }
+
+    /// Elevator status uses the advisory cadence, `None` until the last fetch is old enough
+    pub fn elevators_url(&self, current_time_microsec: u64) -> Option<String> {
+        if !Self::advisory_fetch_due(self.elevators_fetch_time, current_time_microsec) {
+            return None;
+        }
+        self.provider.elevators_url()
+    }
+
+    pub fn received_elevators_response(&mut self, response: Result<Vec<u8>>, current_time_microsec: u64) {
+        self.elevators_fetch_time = Some(current_time_microsec);
+        let outages = response
+            .map_err(ApiError::from)
+            .and_then(|payload| self.provider.parse_elevator_outages(&payload));
+        match outages {
+            Ok(outages) => self.elevator_outages = outages,
+            Err(error) => log::error!("Elevator status fetch failed: {}", error),
+        }
+    }
+
+    pub fn elevator_outages(&self) -> &[ElevatorOutage] {
+        &self.elevator_outages
+    }
+
     pub fn get_current_led_buffer(&mut self, current_time_microsec: u64) -> LEDBuffer {
         let mut etd_led = ETDLEDs::new();
-        let elapsed_since_fetch_microsec = current_time_microsec - self.last_fetch_time;
-        etd_led.update(&self.etd_mins, elapsed_since_fetch_microsec);
+        let scheduled = self.scheduled_departures(current_time_microsec);
+        match (scheduled.as_ref(), self.first_scheduled.as_ref()) {
+            (Some(scheduled), _) => etd_led.update(scheduled, 0, &self.display_config),
+            (None, Some(first_scheduled)) if self.service_status == ServiceStatus::NoService => {
+                etd_led = ETDLEDs::tinted(SCHEDULED_TINT);
+                let elapsed_since_fetch_microsec = current_time_microsec - self.schedule_fetch_time;
+                etd_led.update(std::slice::from_ref(first_scheduled), elapsed_since_fetch_microsec, &self.display_config);
+            }
+            _ => {
+                //Count down from when the agency built the response rather than when it got here
+                let elapsed_since_fetch_microsec = current_time_microsec - self.last_fetch_time + self.response_age_microsec;
+                etd_led.update(&self.departures, elapsed_since_fetch_microsec, &self.display_config);
+            }
+        }
+        //Dark rings overnight look like a dead device, show the time instead once we know it
+        let mut clock_face = self.local_time(current_time_microsec)
+            .filter(|_| !etd_led.shows_trains())
+            .map(ClockFace::new);
+        let leave_now_min = self.display_config.leave_now_min;
+        let mut urgency_animation = etd_led.leave_in()
+            .filter(|(_, leave_in_min)| leave_now_min > 0 && *leave_in_min <= leave_now_min as f32)
+            .map(|(ring, leave_in_min)| UrgencyAnimation::new(ring, leave_in_min));
         let mut pipeline = vec![&mut etd_led as &mut dyn PipelineStep];
+        if let Some(clock_face) = clock_face.as_mut() {
+            pipeline.push(clock_face);
+        }
+        if let Some(urgency_animation) = urgency_animation.as_mut() {
+            pipeline.push(urgency_animation);
+        }
+        let mut no_service = NoServiceIndicator::new();
+        let mut scheduled_marker = ScheduledMarker::new();
+        if scheduled.is_some() {
+            pipeline.push(&mut scheduled_marker);
+        } else if self.service_status == ServiceStatus::NoService {
+            pipeline.push(&mut no_service);
+        }
+        let mut advisory_indicator = AdvisoryIndicator::new();
+        if !self.advisories.is_empty() {
+            pipeline.push(&mut advisory_indicator);
+        }
+        let mut elevator_marker = ElevatorMarker::new();
+        if !self.elevator_outages.is_empty() {
+            pipeline.push(&mut elevator_marker);
+        }
+        let mut cancelled_indicator = CancelledIndicator::new();
+        if self.showing_cancellation(current_time_microsec) {
+            pipeline.push(&mut cancelled_indicator);
+        }
+        let mut error_indicator = self.last_error.as_ref().map(|error| ErrorIndicator::new(error.is_configuration_error()));
+        if let Some(error_indicator) = error_indicator.as_mut() {
+            pipeline.push(error_indicator);
+        }
         if let Some(animation) = self.network_animation.as_mut() {
             pipeline.push(animation);
         }
@@ -71,6 +306,18 @@ impl AppState {
         LEDBuffer::process_pipeline(pipeline, current_time_microsec)
     }
 
+    pub fn service_status(&self) -> ServiceStatus {
+        self.service_status
+    }
+
+    pub fn notices(&self) -> &[ApiNotice] {
+        &self.notices
+    }
+
+    pub fn last_error(&self) -> Option<&ApiError> {
+        self.last_error.as_ref()
+    }
+
     pub fn motion_sensed(&mut self, now: Duration) -> bool {
         let was_sleeping = !self.should_perform_fetch(now);
         self.last_motion_sensed = now;
@@ -82,43 +329,100 @@ impl AppState {
         elapsed.as_secs() < NETWORK_SLEEP_TIME_MIN * 60
     }
 
-    fn update_state(&mut self, json: Top) {
-        self.etd_mins = json.root
-            .station
-            .into_iter()
-            .flat_map(|station| {
-                station.etd
-            })
-            .filter(|station| {
-                station.abbreviation == "MLBR" || station.abbreviation == "SFIA"
+    /// Cancelled trains never make it onto the rings, the center flashes for a little while after a fetch reports one instead
+    fn showing_cancellation(&self, current_time_microsec: u64) -> bool {
+        let elapsed_since_fetch_sec = current_time_microsec.saturating_sub(self.last_fetch_time) / MICROSEC_PER_SEC;
+        elapsed_since_fetch_sec < CANCELLED_INDICATOR_SEC
+            && self.departures.iter().any(|departure| departure.state == DepartureState::Cancelled)
+    }
 
-            })
-            .flat_map(|etd| {
-                etd.estimate
-            })
-            .filter_map(| esd| {
-                esd.minutes.parse::<i32>().ok() //esd.mintues can be "Leaving" need to filter those out
-            })
-            .collect::<Vec<i32>>();
+    fn advisory_fetch_due(fetch_time: Option<u64>, current_time_microsec: u64) -> bool {
+        let Some(fetch_time) = fetch_time else {
+            return true;
+        };
+        let elapsed_sec = current_time_microsec.saturating_sub(fetch_time) / MICROSEC_PER_SEC;
+        elapsed_sec >= FETCH_ADVISORIES_TIME_MIN * 60
+    }
 
-        self.etd_mins.sort_by(|a, b| {
-            a.cmp(b)
+    fn scheduled_departures(&self, current_time_microsec: u64) -> Option<Vec<Departure>> {
+        if !self.showing_static_timetable(current_time_microsec) {
+            return None;
+        }
+        let local_time = self.local_time(current_time_microsec)?;
+        let mut departures = self.static_timetable.upcoming(local_time, STATIC_HORIZON_MIN);
+        departures.retain(|departure| self.provider.shows_destination(&departure.abbreviation));
+        Some(departures)
+    }
+
+    /// Zero without a synced clock, the countdown then starts when the response arrived
+    fn response_age_microsec(&self, current_time_microsec: u64) -> u64 {
+        let (Some(boot_unix_sec), Some(response_unix_sec)) = (self.boot_unix_sec, self.response_unix_sec) else {
+            return 0;
+        };
+        let now_unix_microsec = boot_unix_sec * MICROSEC_PER_SEC as i64 + current_time_microsec as i64;
+        let age_microsec = now_unix_microsec - response_unix_sec * MICROSEC_PER_SEC as i64;
+        match u64::try_from(age_microsec) {
+            Ok(age_microsec) if age_microsec <= MAX_RESPONSE_AGE_SEC * MICROSEC_PER_SEC => age_microsec,
+            _ => 0,
+        }
+    }
+
+    fn update_state(&mut self, snapshot: FeedSnapshot) {
+        self.notices = snapshot.notices;
+        self.response_unix_sec = snapshot.response_unix_sec;
+        self.departures = snapshot.departures;
+
+        //Cancelled trains have no minutes and sort to the end
+        self.departures.sort_by_key(|departure| {
+            departure.state.minutes().unwrap_or(u32::MAX)
         });
-        log::info!("Esimates {:?}", self.etd_mins);
+        log::info!("Esimates {:?}", self.departures.iter().map(|departure| departure.state).collect::<Vec<DepartureState>>());
+        self.service_status = if self.departures.is_empty() {
+            ServiceStatus::NoService
+        } else {
+            self.first_scheduled = None;
+            ServiceStatus::Running
+        };
+
+    }
 
+    fn process_response(&mut self, response: Result<Vec<u8>>) -> Result<(), ApiError> {
+        let payload = response?;
+        let snapshot = self.provider.parse_departures(&payload)?;
+        self.update_state(snapshot);
+        Ok(())
     }
 
-    fn parse_json(&self, response: String) -> Result<Top, serde_json::Error> {
-        serde_json::from_str(&response)
+    fn retry_time(error: &ApiError) -> i32 {
+        match error {
+            ApiError::Transport(_) => FETCH_RETRY_TIME_MIN,
+            ApiError::ServerError(_) | ApiError::Schema(_) => FETCH_SERVER_ERROR_TIME_MIN,
+            ApiError::RateLimited => FETCH_RATE_LIMITED_TIME_MIN,
+            //Asking again every couple of minutes won't fix the config, but someone might fix it eventually
+            ApiError::InvalidApiKey
+            | ApiError::UnknownStation
+            | ApiError::MalformedRequest(_)
+            | ApiError::ClientError(_) => FETCH_CONFIGURATION_ERROR_TIME_MIN,
+        }
     }
 
     fn next_fetch_time(&self) -> i32 {
-        match self.etd_mins.len() {
+        if self.service_status == ServiceStatus::NoService {
+            return FETCH_NO_SERVICE_TIME_MIN;
+        }
+        //Leaving and cancelled trains don't tell us anything about when to check again
+        let upcoming: Vec<i32> = self.departures.iter()
+            .filter_map(|departure| match departure.state {
+                DepartureState::Minutes(minutes) => Some(minutes as i32),
+                _ => None
+            })
+            .collect();
+        match upcoming.len() {
             0..=1 => {
                 FETCH_REFRESH_TIME_MIN
             }
             2 => {
-                let next_train = self.etd_mins[0];
+                let next_train = upcoming[0];
                 let before_next_train = next_train - FETCH_NEXT_TRAIN_TIME_MIN;
                 before_next_train.clamp(0, FETCH_CORRECTION_TIME_MIN)
             }
@@ -130,30 +434,3 @@ impl AppState {
     }
 
 }
-
-#[derive(Deserialize, Debug)]
-struct Top {
-    root: Root
-}
-
-#[derive(Deserialize, Debug)]
-struct Root {
-    station: Vec<Station>
-}
-
-
-#[derive(Deserialize, Debug)]
-struct Station {
-    etd: Vec<Etd>
-}
-
-#[derive(Deserialize, Debug)]
-struct Etd {
-    abbreviation: String,
-    estimate: Vec<Estimate>
-}
-
-#[derive(Deserialize, Debug)]
-struct Estimate {
-    minutes: String,
-}
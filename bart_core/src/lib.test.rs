@@ -1,21 +1,50 @@
-use crate::AppState;
+use crate::{Advisory, ApiNotice, DisplayConfig, DisplayMode, OverflowStrategy, RingFilter, TimeScale, WalkTime, ElevatorOutage, AppState, ApiError, BartProvider, DepartureState, DestinationFilter, Direction, ServiceStatus, StaticTimetable, StationConfig, TransitProvider};
 use smart_leds::RGB8;
 use smart_leds::colors;
 use std::time::Duration;
 mod fixtures;
+use fixtures::Estimate;
+//The fixtures' SFIA and PITT trains are on the yellow line (#ffff33), MLBR trains on the red line
+static YELLOW_LINE_COLOR: RGB8 = RGB8 {r: 16, g: 16, b: 3};
+static RED_LINE_COLOR: RGB8 = RGB8 {r: 16, g: 0, b: 0};
+static DELAY_COLOR: RGB8 = RGB8 {r: 16, g: 10, b: 0};
+
+//Center ring markers
+static ERROR_COLOR: RGB8 = RGB8 {r: 16, g: 0, b: 0};
+static SCHEDULED_COLOR: RGB8 = RGB8 {r: 0, g: 16, b: 16};
+static NO_SERVICE_COLOR: RGB8 = RGB8 {r: 1, g: 1, b: 7};
+static ELEVATOR_COLOR: RGB8 = RGB8 {r: 16, g: 0, b: 16};
+
+//Wall clock times for the timetable, station schedule and clock face
+//Wednesday 2024-07-03 07:15 PDT
+static WEDNESDAY_MORNING_UNIX_SEC: u64 = 1720016100;
+//Saturday 2024-07-06 00:10 PDT
+static SATURDAY_AFTER_MIDNIGHT_UNIX_SEC: u64 = 1720249800;
+//Wednesday 2024-07-03 04:40 PDT
+static BEFORE_FIRST_TRAIN_UNIX_SEC: u64 = 1720006800;
+static FIFTEEN_MIN_MICRO: u64 = 1000000 * 60 * 15;
+
+//Static timetable: 07:20, 07:25 and 07:31 inbound, and 24:25 (Friday night into Saturday)
+//PITT isn't in new_app_state's destinations
+static SCHEDULE_DESTINATIONS: [&str; 3] = ["SFIA", "MLBR", "PITT"];
+static WEEKDAY_SCHEDULE: [(u16, u8); 4] = [(440, 0), (445 | 0x8000, 2), (451 | 0x8000, 1), (1465, 0)];
+static SATURDAY_SCHEDULE: [(u16, u8); 1] = [(20, 0)];
+static WEEK_SCHEDULE: [&[(u16, u8)]; 7] = [&[], &WEEKDAY_SCHEDULE, &WEEKDAY_SCHEDULE, &WEEKDAY_SCHEDULE, &WEEKDAY_SCHEDULE, &WEEKDAY_SCHEDULE, &SATURDAY_SCHEDULE];
+//Timetable departures don't know their line
 static LED_COLOR: RGB8 = RGB8 {r: 16, g: 16, b: 16};
+static SCHEDULED_TINT_COLOR: RGB8 = RGB8 {r: 1, g: 9, b: 16};
 
 
 
 #[test]
 fn test_two_rings_lit() {
-    let mut app_state = AppState::new(launch_time());
+    let mut app_state = new_app_state();
     app_state.received_http_response(fixtures::json_with_etd_3_trains("4", "15"), 0);
     let led_buffer = app_state.get_current_led_buffer(0);
 
     let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
-    fill_inner_ring::<4>(&mut expected_buffer, LED_COLOR);
-    fill_outer_ring::<15>(&mut expected_buffer, LED_COLOR);
+    fill_inner_ring::<4>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    fill_outer_ring::<15>(&mut expected_buffer, YELLOW_LINE_COLOR);
 
     assert_eq!(expected_buffer, led_buffer.rgb_buffer);
 }
@@ -23,62 +52,99 @@ fn test_two_rings_lit() {
 // in the array (ie to we clear to the buffer to None before filling)
 #[test]
 fn test_two_rings_lit_after_2_min() {
-    let mut app_state = AppState::new(launch_time());
+    let mut app_state = new_app_state();
     app_state.received_http_response(fixtures::json_with_etd_3_trains("4", "15"), 0);
     let two_min_micro = 1000000 * 60 * 2;
     let led_buffer = app_state.get_current_led_buffer(two_min_micro);
 
     let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
-    fill_inner_ring::<2>(&mut expected_buffer, LED_COLOR);
-    fill_outer_ring::<13>(&mut expected_buffer, LED_COLOR);
+    fill_inner_ring::<2>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    fill_outer_ring::<13>(&mut expected_buffer, YELLOW_LINE_COLOR);
 
     assert_eq!(expected_buffer, led_buffer.rgb_buffer);
 }
 
 #[test]
 fn test_two_rings_lit_first_train_left() {
-    let mut app_state = AppState::new(launch_time());
+    let mut app_state = new_app_state();
     app_state.received_http_response(fixtures::json_with_etd_3_trains("4", "15"), 0);
     let five_min_micro = 1000000 * 60 * 5;
     let led_buffer = app_state.get_current_led_buffer(five_min_micro);
 
     let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
-    fill_inner_ring::<10>(&mut expected_buffer, LED_COLOR);
-    fill_outer_ring::<18>(&mut expected_buffer, LED_COLOR);
+    fill_inner_ring::<10>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    fill_outer_ring::<18>(&mut expected_buffer, YELLOW_LINE_COLOR);
 
     assert_eq!(expected_buffer, led_buffer.rgb_buffer);
 }
 
 #[test]
 fn test_shortest_etd_too_long_for_inner_ring() {
-    let mut app_state = AppState::new(launch_time());
+    let mut app_state = new_app_state();
     app_state.received_http_response(fixtures::json_with_etd_3_trains("17", "20"), 0);
     let led_buffer = app_state.get_current_led_buffer(0);
 
     let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
 
-    fill_outer_ring::<17>(&mut expected_buffer, LED_COLOR);
+    fill_outer_ring::<17>(&mut expected_buffer, YELLOW_LINE_COLOR);
 
     assert_eq!(expected_buffer, led_buffer.rgb_buffer);
 }
 
 #[test]
 fn test_etd_is_leaving() {
-    let mut app_state = AppState::new(launch_time());
+    //The leaving train blinks the first inner LED and the next train moves to the outer ring
+    let mut app_state = new_app_state();
     app_state.received_http_response(fixtures::json_with_etd_3_trains("Leaving", "15"), 0);
     let led_buffer = app_state.get_current_led_buffer(0);
 
     let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
-    fill_inner_ring::<15>(&mut expected_buffer, LED_COLOR);
-    fill_outer_ring::<23>(&mut expected_buffer, LED_COLOR);
+    fill_inner_ring::<1>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    fill_outer_ring::<15>(&mut expected_buffer, YELLOW_LINE_COLOR);
+
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_etd_is_leaving_blink_off() {
+    let mut app_state = new_app_state();
+    app_state.received_http_response(fixtures::json_with_etd_3_trains("Leaving", "15"), 0);
+    let half_sec_micro = 500000;
+    let led_buffer = app_state.get_current_led_buffer(half_sec_micro);
+
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_outer_ring::<15>(&mut expected_buffer, YELLOW_LINE_COLOR);
+
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_etd_is_leaving_after_1_min() {
+    //Once the leaving train is gone the rings go back to the next two trains
+    let mut app_state = new_app_state();
+    app_state.received_http_response(fixtures::json_with_etd_3_trains("Leaving", "15"), 0);
+    let one_min_micro = 1000000 * 60;
+    let led_buffer = app_state.get_current_led_buffer(one_min_micro);
+
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<14>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    fill_outer_ring::<22>(&mut expected_buffer, YELLOW_LINE_COLOR);
 
     assert_eq!(expected_buffer, led_buffer.rgb_buffer);
 }
 
+#[test]
+fn test_next_fetch_time_ignores_leaving() {
+    //Only the 15 min train counts, so fetch in 5 min
+    let mut app_state = new_app_state();
+    let next_fetch_sec = app_state.received_http_response(fixtures::json_with_etd_2_trains("Leaving", "15"), 0);
+    assert_eq!(next_fetch_sec, 300);
+}
+
 #[test]
 fn test_next_fetch_time_2_trains() {
     //We should fetch 2 min before next train leaves (4 -2) * 60 = 120
-    let mut app_state = AppState::new(launch_time());
+    let mut app_state = new_app_state();
     let next_fetch_sec = app_state.received_http_response(fixtures::json_with_etd_2_trains("4", "15"), 0);
     assert_eq!(next_fetch_sec, 120);
 }
@@ -86,7 +152,7 @@ fn test_next_fetch_time_2_trains() {
 #[test]
 fn test_next_fetch_time_2_trains_beyond_max() {
     //The most time we should wait between fetches is 10 min (600) CLAMP((13 - 2), 10) * 60 = 600
-    let mut app_state = AppState::new(launch_time());
+    let mut app_state = new_app_state();
     let next_fetch_sec = app_state.received_http_response(fixtures::json_with_etd_2_trains("13", "15"), 0);
     assert_eq!(next_fetch_sec, 600);
 }
@@ -94,7 +160,7 @@ fn test_next_fetch_time_2_trains_beyond_max() {
 #[test]
 fn test_next_fetch_time_1_train() {
     //If there's only 1 train scheduled, fetch in 5 min
-    let mut app_state = AppState::new(launch_time());
+    let mut app_state = new_app_state();
     let next_fetch_sec = app_state.received_http_response(fixtures::json_with_etd_1_train("4"), 0);
     assert_eq!(next_fetch_sec, 300);
 }
@@ -102,29 +168,208 @@ fn test_next_fetch_time_1_train() {
 #[test]
 fn test_next_fetch_time_3_train() {
     //If there's 3 or more trains, fetch in 10 min
-    let mut app_state = AppState::new(launch_time());
+    let mut app_state = new_app_state();
     let next_fetch_sec = app_state.received_http_response(fixtures::json_with_etd_3_trains("4", "15"), 0);
     assert_eq!(next_fetch_sec, 600);
 }
 //TODO test network activity state
 
+#[test]
+fn test_destination_deny_list() {
+    //Everything but Antioch and Pittsburg/Bay Point, so only the SFIA trains are left
+    let config = StationConfig::new("ROCK", "!ANTC,PITT".parse().unwrap());
+    let mut app_state = bart_app_state(config);
+    app_state.received_http_response(fixtures::json_with_etd_3_trains("4", "15"), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<4>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    fill_outer_ring::<15>(&mut expected_buffer, YELLOW_LINE_COLOR);
+
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_destination_all() {
+    //The Pittsburg/Bay Point train at 11 min is now the second train, it's running 6 min (403 sec) late
+    let config = StationConfig::new("ROCK", DestinationFilter::All);
+    let mut app_state = bart_app_state(config);
+    app_state.received_http_response(fixtures::json_with_etd_3_trains("4", "15"), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<4>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    fill_outer_ring::<11>(&mut expected_buffer, DELAY_COLOR);
+    fill_outer_ring::<5>(&mut expected_buffer, YELLOW_LINE_COLOR);
+
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_other_origin_station_ignored() {
+    let config = StationConfig::new("MCAR", DestinationFilter::All);
+    let mut app_state = bart_app_state(config);
+    app_state.received_http_response(fixtures::json_with_etd_3_trains("4", "15"), 0);
+
+    assert!(app_state.departures.is_empty());
+    assert_eq!(app_state.service_status(), ServiceStatus::NoService);
+}
+
+#[test]
+fn test_bart_departures_url() {
+    let app_state = new_app_state();
+    assert_eq!(app_state.departures_url(), "https://api.bart.gov/api/etd.aspx?cmd=etd&orig=ROCK&key=MW9S-E7SL-26DU-VV8V&json=y");
+}
+
+#[test]
+fn test_parse_destination_filter() {
+    assert_eq!("*".parse::<DestinationFilter>().unwrap(), DestinationFilter::All);
+    assert_eq!("".parse::<DestinationFilter>().unwrap(), DestinationFilter::All);
+    assert_eq!("mlbr, SFIA".parse::<DestinationFilter>().unwrap(), DestinationFilter::Allow(vec!["MLBR".to_string(), "SFIA".to_string()]));
+    assert_eq!("!ANTC".parse::<DestinationFilter>().unwrap(), DestinationFilter::Deny(vec!["ANTC".to_string()]));
+    assert_eq!(",".parse::<DestinationFilter>().unwrap(), DestinationFilter::All);
+    assert_eq!(" , ".parse::<DestinationFilter>().unwrap(), DestinationFilter::All);
+    assert_eq!("!".parse::<DestinationFilter>().unwrap(), DestinationFilter::All);
+}
+
+#[test]
+fn test_no_service() {
+    let mut app_state = new_app_state();
+    let next_fetch_sec = app_state.received_http_response(fixtures::json_no_service(), 0);
+    assert_eq!(next_fetch_sec, 15 * 60);
+    assert_eq!(app_state.service_status(), ServiceStatus::NoService);
+    assert_eq!(app_state.notices(), [ApiNotice::Warning("No data matched your criteria.".to_string())]);
+
+    let led_buffer = app_state.get_current_led_buffer(0);
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    expected_buffer[40..].clone_from_slice(&[RGB8 {r: 1, g: 1, b: 7}; 4]);
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_service_resumes() {
+    let mut app_state = new_app_state();
+    app_state.received_http_response(fixtures::json_no_service(), 0);
+    app_state.received_http_response(fixtures::json_with_etd_3_trains("4", "15"), 0);
+    assert_eq!(app_state.service_status(), ServiceStatus::Running);
+    assert!(app_state.notices().is_empty());
+}
+
+#[test]
+fn test_invalid_api_key() {
+    //A revoked key won't fix itself, so back off for an hour and light the whole center red
+    let mut app_state = new_app_state();
+    let next_fetch_sec = app_state.received_http_response(fixtures::json_api_error("Invalid key", "The api key was missing or invalid."), 0);
+    assert_eq!(next_fetch_sec, 60 * 60);
+    assert_eq!(app_state.last_error(), Some(&ApiError::InvalidApiKey));
+    //The provider turns the error notice into the error, it isn't kept as a notice
+    assert!(app_state.notices().is_empty());
+
+    let led_buffer = app_state.get_current_led_buffer(0);
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    expected_buffer[40..].clone_from_slice(&[ERROR_COLOR; 4]);
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_provider_reports_notice_error() {
+    let provider = BartProvider::new("MW9S-E7SL-26DU-VV8V", StationConfig::new("ROCK", DestinationFilter::All));
+    let payload = fixtures::json_api_error("Invalid key", "The api key was missing or invalid.").unwrap();
+    assert_eq!(provider.parse_departures(&payload), Err(ApiError::InvalidApiKey));
+}
+
+#[test]
+fn test_unknown_station() {
+    let mut app_state = new_app_state();
+    let next_fetch_sec = app_state.received_http_response(fixtures::json_api_error("Invalid orig", "The orig station parameter XXXX is missing or invalid."), 0);
+    assert_eq!(next_fetch_sec, 60 * 60);
+    assert_eq!(app_state.last_error(), Some(&ApiError::UnknownStation));
+}
+
+#[test]
+fn test_transport_error() {
+    //Keep showing what we had and retry soon
+    let mut app_state = new_app_state();
+    app_state.received_http_response(fixtures::json_with_etd_3_trains("4", "15"), 0);
+    let next_fetch_sec = app_state.received_http_response(Err(anyhow::anyhow!("connection reset")), 0);
+    assert_eq!(next_fetch_sec, 120);
+    assert!(matches!(app_state.last_error(), Some(ApiError::Transport(_))));
+
+    let led_buffer = app_state.get_current_led_buffer(0);
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<4>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    fill_outer_ring::<15>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    expected_buffer[40] = ERROR_COLOR;
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_http_status_errors() {
+    let mut app_state = new_app_state();
+    let next_fetch_sec = app_state.received_http_response(Err(ApiError::from_status(429).into()), 0);
+    assert_eq!(next_fetch_sec, 15 * 60);
+    assert_eq!(app_state.last_error(), Some(&ApiError::RateLimited));
+
+    let next_fetch_sec = app_state.received_http_response(Err(ApiError::from_status(503).into()), 0);
+    assert_eq!(next_fetch_sec, 5 * 60);
+    assert_eq!(app_state.last_error(), Some(&ApiError::ServerError(503)));
+
+    let next_fetch_sec = app_state.received_http_response(Err(ApiError::from_status(403).into()), 0);
+    assert_eq!(next_fetch_sec, 60 * 60);
+    assert_eq!(app_state.last_error(), Some(&ApiError::ClientError(403)));
+}
+
+#[test]
+fn test_schema_mismatch() {
+    let mut app_state = new_app_state();
+    let next_fetch_sec = app_state.received_http_response(Ok(b"{\"root\": {\"station\": \"ROCK\"}}".to_vec()), 0);
+    assert_eq!(next_fetch_sec, 5 * 60);
+    assert!(matches!(app_state.last_error(), Some(ApiError::Schema(_))));
+
+    app_state.received_http_response(fixtures::json_with_etd_3_trains("4", "15"), 0);
+    assert_eq!(app_state.last_error(), None);
+}
+
+#[test]
+fn test_departure_metadata() {
+    let mut app_state = bart_app_state(StationConfig::new("ROCK", DestinationFilter::All));
+    app_state.received_http_response(fixtures::json_with_etd_3_trains("4", "15"), 0);
+
+    let pittsburg = &app_state.departures[1];
+    assert_eq!(pittsburg.state, DepartureState::Minutes(11));
+    assert_eq!(pittsburg.destination, "Pittsburg/Bay Point");
+    assert_eq!(pittsburg.abbreviation, "PITT");
+    assert_eq!(pittsburg.platform, 1);
+    assert_eq!(pittsburg.direction, Direction::North);
+    assert_eq!(pittsburg.car_count, 8);
+    assert_eq!(pittsburg.color, "YELLOW");
+    assert_eq!(pittsburg.hexcolor, Some(RGB8 {r: 0xff, g: 0xff, b: 0x33}));
+    assert!(pittsburg.bike_allowed);
+    assert_eq!(pittsburg.delay, Duration::from_secs(403));
+
+    let airport = &app_state.departures[0];
+    assert_eq!(airport.abbreviation, "SFIA");
+    assert_eq!(airport.direction, Direction::South);
+    assert_eq!(airport.platform, 2);
+}
+
 #[test]
 fn test_should_allow_fetch_on_launch() {
-    let app_state = AppState::new(launch_time());
+    let app_state = new_app_state();
     let one_min_duration = Duration::new(60, 0);
     assert!(app_state.should_perform_fetch(one_min_duration));
 }
 
 #[test]
 fn test_should_not_allow_fetch_after_10_min() {
-    let app_state = AppState::new(launch_time());
+    let app_state = new_app_state();
     let ten_min_duration = Duration::new(10 * 60, 0);
     assert!(!app_state.should_perform_fetch(ten_min_duration));
 }
 
 #[test]
 fn test_should_allow_fetch_after_motion_sensed() {
-    let mut app_state = AppState::new(launch_time());
+    let mut app_state = new_app_state();
     let eight_min_duration = Duration::new(8 * 60, 0);
     let was_sleeping = app_state.motion_sensed(eight_min_duration);
     let ten_min_duration = Duration::new(10 * 60, 0);
@@ -134,7 +379,7 @@ fn test_should_allow_fetch_after_motion_sensed() {
 
 #[test]
 fn test_was_sleeping_after_10_min() {
-    let mut app_state = AppState::new(launch_time());
+    let mut app_state = new_app_state();
     let eleven_min_duration = Duration::new(11 * 60, 0);
     let was_sleeping = app_state.motion_sensed(eleven_min_duration);
     assert!(was_sleeping);
@@ -143,14 +388,1233 @@ fn test_was_sleeping_after_10_min() {
     assert!(!was_sleeping_2);
 }
 
-fn fill_outer_ring<const N: usize>(buffer: &mut [RGB8; 44], color: RGB8) {
-    buffer[..N].clone_from_slice(&[color; N]);
+#[test]
+fn test_static_timetable_after_live_data_lost() {
+    let mut app_state = scheduled_app_state(WEDNESDAY_MORNING_UNIX_SEC);
+    app_state.received_http_response(Err(anyhow::anyhow!("Connection refused")), 0);
+    assert!(app_state.showing_static_timetable(FIFTEEN_MIN_MICRO));
+    let led_buffer = app_state.get_current_led_buffer(FIFTEEN_MIN_MICRO);
+
+    //07:15 local, trains at 07:20 and 07:31
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<5>(&mut expected_buffer, LED_COLOR);
+    fill_outer_ring::<16>(&mut expected_buffer, LED_COLOR);
+    expected_buffer[40] = ERROR_COLOR;
+    expected_buffer[41] = SCHEDULED_COLOR;
+
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
 }
 
-fn fill_inner_ring<const N: usize>(buffer: &mut [RGB8; 44], color: RGB8) {
-    buffer[24..24+N].clone_from_slice(&[color; N]);
+#[test]
+fn test_static_timetable_with_elevator_out() {
+    let mut app_state = scheduled_app_state(WEDNESDAY_MORNING_UNIX_SEC);
+    app_state.received_http_response(Err(anyhow::anyhow!("Connection refused")), 0);
+    app_state.received_elevators_response(fixtures::json_elevators("The Rockridge Street elevator is out of service."), 0);
+    let led_buffer = app_state.get_current_led_buffer(FIFTEEN_MIN_MICRO);
+
+    //Each center marker keeps its own LED
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<5>(&mut expected_buffer, LED_COLOR);
+    fill_outer_ring::<16>(&mut expected_buffer, LED_COLOR);
+    expected_buffer[40] = ERROR_COLOR;
+    expected_buffer[41] = SCHEDULED_COLOR;
+    expected_buffer[43] = ELEVATOR_COLOR;
+
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
 }
 
-fn launch_time() -> Duration {
-    Duration::new(0, 0)
-} 
+#[test]
+fn test_static_timetable_waits_for_threshold() {
+    let mut app_state = scheduled_app_state(WEDNESDAY_MORNING_UNIX_SEC);
+    app_state.received_http_response(fixtures::json_with_etd_3_trains("4", "15"), 0);
+    let fourteen_min_micro = 1000000 * 60 * 14;
+    assert!(!app_state.showing_static_timetable(fourteen_min_micro));
+    assert!(app_state.showing_static_timetable(FIFTEEN_MIN_MICRO));
+
+    app_state.received_http_response(fixtures::json_with_etd_3_trains("4", "15"), FIFTEEN_MIN_MICRO);
+    let led_buffer = app_state.get_current_led_buffer(FIFTEEN_MIN_MICRO);
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<4>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    fill_outer_ring::<15>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_static_timetable_needs_wall_clock() {
+    let mut app_state = new_app_state();
+    app_state.set_static_timetable(weekday_timetable());
+    assert!(!app_state.showing_static_timetable(FIFTEEN_MIN_MICRO));
+    let led_buffer = app_state.get_current_led_buffer(FIFTEEN_MIN_MICRO);
+    assert_eq!([colors::BLACK; 44], led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_static_timetable_empty() {
+    let mut app_state = scheduled_app_state(WEDNESDAY_MORNING_UNIX_SEC);
+    app_state.set_static_timetable(StaticTimetable::new([&[]; 7], &[], [Direction::South, Direction::North]));
+    assert!(!app_state.showing_static_timetable(FIFTEEN_MIN_MICRO));
+}
+
+#[test]
+fn test_static_timetable_split() {
+    let mut app_state = app_state_with(DisplayConfig {
+        mode: DisplayMode::Split { inner: RingFilter::Direction(Direction::North), outer: RingFilter::Direction(Direction::South) },
+        ..DisplayConfig::default()
+    });
+    app_state.set_static_timetable(weekday_timetable());
+    app_state.time_synced(Duration::from_secs(WEDNESDAY_MORNING_UNIX_SEC - 15 * 60), 0);
+    let led_buffer = app_state.get_current_led_buffer(FIFTEEN_MIN_MICRO);
+
+    //07:15 local, PITT at 07:25 north and SFIA at 07:20 south. Every destination is allowed this time
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<10>(&mut expected_buffer, LED_COLOR);
+    fill_outer_ring::<5>(&mut expected_buffer, LED_COLOR);
+    expected_buffer[41] = SCHEDULED_COLOR;
+
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_static_timetable_past_midnight() {
+    let mut app_state = scheduled_app_state(SATURDAY_AFTER_MIDNIGHT_UNIX_SEC);
+    let led_buffer = app_state.get_current_led_buffer(FIFTEEN_MIN_MICRO);
+
+    //00:10 Saturday, Saturday's 00:20 train and Friday's 24:25 train
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<10>(&mut expected_buffer, LED_COLOR);
+    fill_outer_ring::<15>(&mut expected_buffer, LED_COLOR);
+    expected_buffer[41] = SCHEDULED_COLOR;
+
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_scheduled_first_train() {
+    let mut app_state = early_morning_app_state();
+    app_state.received_http_response(fixtures::json_no_service(), 0);
+    assert_eq!(app_state.schedule_url().unwrap(), "https://api.bart.gov/api/sched.aspx?cmd=stnsched&orig=ROCK&key=MW9S-E7SL-26DU-VV8V&json=y");
+    app_state.received_schedule_response(fixtures::json_station_schedule(&[("PITT", "4:41 AM"), ("SFIA", "4:48 AM"), ("MLBR", "5:03 AM")]), 0);
+
+    let first_scheduled = app_state.first_scheduled().unwrap();
+    assert_eq!(first_scheduled.abbreviation, "SFIA");
+    assert_eq!(first_scheduled.state, DepartureState::Minutes(8));
+    assert!(first_scheduled.bike_allowed);
+
+    let led_buffer = app_state.get_current_led_buffer(0);
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<8>(&mut expected_buffer, SCHEDULED_TINT_COLOR);
+    expected_buffer[40..].clone_from_slice(&[NO_SERVICE_COLOR; 4]);
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_scheduled_counts_down() {
+    let mut app_state = early_morning_app_state();
+    app_state.received_http_response(fixtures::json_no_service(), 0);
+    app_state.received_schedule_response(fixtures::json_station_schedule(&[("SFIA", "4:48 AM")]), 0);
+    let two_min_micro = 1000000 * 60 * 2;
+    let led_buffer = app_state.get_current_led_buffer(two_min_micro);
+
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<6>(&mut expected_buffer, SCHEDULED_TINT_COLOR);
+    expected_buffer[40..].clone_from_slice(&[NO_SERVICE_COLOR; 4]);
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_scheduled_first_train_split() {
+    let mut app_state = app_state_with(DisplayConfig {
+        mode: DisplayMode::Split { inner: RingFilter::Direction(Direction::North), outer: RingFilter::Direction(Direction::South) },
+        ..DisplayConfig::default()
+    });
+    app_state.time_synced(Duration::from_secs(BEFORE_FIRST_TRAIN_UNIX_SEC), 0);
+    app_state.received_http_response(fixtures::json_no_service(), 0);
+    app_state.received_schedule_response(fixtures::json_station_schedule(&[("SFIA", "4:48 AM")]), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+
+    //The station schedule doesn't say which way the train goes, so both rings count down to it
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<8>(&mut expected_buffer, SCHEDULED_TINT_COLOR);
+    fill_outer_ring::<8>(&mut expected_buffer, SCHEDULED_TINT_COLOR);
+    expected_buffer[40..].clone_from_slice(&[NO_SERVICE_COLOR; 4]);
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_schedule_needs_wall_clock() {
+    let mut app_state = new_app_state();
+    app_state.received_http_response(fixtures::json_no_service(), 0);
+    assert_eq!(app_state.schedule_url(), None);
+}
+
+#[test]
+fn test_schedule_not_fetched_while_running() {
+    let mut app_state = early_morning_app_state();
+    app_state.received_http_response(fixtures::json_with_etd_3_trains("4", "15"), 0);
+    assert_eq!(app_state.schedule_url(), None);
+}
+
+#[test]
+fn test_scheduled_cleared_when_service_resumes() {
+    let mut app_state = early_morning_app_state();
+    app_state.received_http_response(fixtures::json_no_service(), 0);
+    app_state.received_schedule_response(fixtures::json_station_schedule(&[("SFIA", "4:48 AM")]), 0);
+    app_state.received_http_response(fixtures::json_with_etd_3_trains("4", "15"), 0);
+    assert!(app_state.first_scheduled().is_none());
+
+    let led_buffer = app_state.get_current_led_buffer(0);
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<4>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    fill_outer_ring::<15>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_schedule_single_item() {
+    let mut app_state = early_morning_app_state();
+    app_state.received_http_response(fixtures::json_no_service(), 0);
+    app_state.received_schedule_response(fixtures::json_station_schedule_single_item(), 0);
+    //04:40 until 23:58
+    assert_eq!(app_state.first_scheduled().unwrap().state, DepartureState::Minutes(1158));
+}
+
+#[test]
+fn test_schedule_api_error() {
+    let mut app_state = early_morning_app_state();
+    app_state.received_http_response(fixtures::json_no_service(), 0);
+    app_state.received_schedule_response(fixtures::json_station_schedule(&[("SFIA", "4:48 AM")]), 0);
+    app_state.received_schedule_response(fixtures::json_api_error("Invalid orig", "The orig station parameter FOO is missing or invalid."), 0);
+    assert!(app_state.first_scheduled().is_none());
+    //The live feed is what decides retries, a bad schedule response doesn't count as an error
+    assert!(app_state.last_error().is_none());
+}
+
+static ADVISORY_COLOR: RGB8 = RGB8 {r: 16, g: 10, b: 0};
+
+#[test]
+fn test_advisory_shown() {
+    let mut app_state = new_app_state();
+    app_state.received_http_response(fixtures::json_with_etd_3_trains("4", "15"), 0);
+    assert_eq!(app_state.advisories_url(0).unwrap(), "https://api.bart.gov/api/bsa.aspx?cmd=bsa&orig=ROCK&key=MW9S-E7SL-26DU-VV8V&json=y");
+    app_state.received_advisories_response(fixtures::json_advisories("BART", "There is a 10-minute delay at Lake Merritt."), 0);
+    assert_eq!(app_state.advisories(), [Advisory {
+        id: "267913".to_string(),
+        station: "BART".to_string(),
+        kind: "DELAY".to_string(),
+        description: "There is a 10-minute delay at Lake Merritt.".to_string(),
+    }]);
+
+    let led_buffer = app_state.get_current_led_buffer(0);
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<4>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    fill_outer_ring::<15>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    expected_buffer[42] = ADVISORY_COLOR;
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+
+    //Blinks once a second
+    let led_buffer = app_state.get_current_led_buffer(1000000);
+    assert_eq!(led_buffer.rgb_buffer[42], colors::BLACK);
+}
+
+#[test]
+fn test_no_advisories() {
+    let mut app_state = new_app_state();
+    app_state.received_advisories_response(fixtures::json_no_advisories(), 0);
+    assert!(app_state.advisories().is_empty());
+    let led_buffer = app_state.get_current_led_buffer(0);
+    assert_eq!([colors::BLACK; 44], led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_advisory_other_station_ignored() {
+    let mut app_state = new_app_state();
+    app_state.received_advisories_response(fixtures::json_advisories("DUBL", "Elevator work at Dublin."), 0);
+    assert!(app_state.advisories().is_empty());
+}
+
+#[test]
+fn test_advisory_cadence() {
+    let mut app_state = new_app_state();
+    app_state.received_advisories_response(fixtures::json_advisories("ROCK", "Single tracking near Rockridge."), 0);
+    let fourteen_min_micro = 1000000 * 60 * 14;
+    assert_eq!(app_state.advisories_url(fourteen_min_micro), None);
+    assert!(app_state.advisories_url(FIFTEEN_MIN_MICRO).is_some());
+}
+
+#[test]
+fn test_advisory_fetch_failed_keeps_last() {
+    let mut app_state = new_app_state();
+    app_state.received_advisories_response(fixtures::json_advisories("BART", "There is a 10-minute delay at Lake Merritt."), 0);
+    app_state.received_advisories_response(Err(anyhow::anyhow!("Connection refused")), FIFTEEN_MIN_MICRO);
+    assert_eq!(app_state.advisories().len(), 1);
+    //A failed fetch still waits out the interval
+    assert_eq!(app_state.advisories_url(FIFTEEN_MIN_MICRO), None);
+}
+
+#[test]
+fn test_elevator_out_at_origin() {
+    let mut app_state = new_app_state();
+    app_state.received_http_response(fixtures::json_with_etd_3_trains("4", "15"), 0);
+    assert_eq!(app_state.elevators_url(0).unwrap(), "https://api.bart.gov/api/bsa.aspx?cmd=elev&key=MW9S-E7SL-26DU-VV8V&json=y");
+    let description = "There are 2 elevators out of service at this time: 12th St. Oakland Platform and Rockridge Street.";
+    app_state.received_elevators_response(fixtures::json_elevators(description), 0);
+    assert_eq!(app_state.elevator_outages(), [ElevatorOutage { station: "ROCK".to_string(), description: description.to_string() }]);
+
+    let led_buffer = app_state.get_current_led_buffer(0);
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<4>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    fill_outer_ring::<15>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    expected_buffer[43] = ELEVATOR_COLOR;
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_elevator_out_at_destination() {
+    let mut app_state = new_app_state();
+    app_state.received_elevators_response(fixtures::json_elevators("There is one elevator out of service at this time: Millbrae Platform."), 0);
+    let stations: Vec<&str> = app_state.elevator_outages().iter().map(|outage| outage.station.as_str()).collect();
+    assert_eq!(stations, ["MLBR"]);
+}
+
+#[test]
+fn test_elevator_out_elsewhere() {
+    let mut app_state = new_app_state();
+    app_state.received_elevators_response(fixtures::json_elevators("There is one elevator out of service at this time: Powell St. Street."), 0);
+    assert!(app_state.elevator_outages().is_empty());
+    let led_buffer = app_state.get_current_led_buffer(0);
+    assert_eq!([colors::BLACK; 44], led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_elevator_deny_list_only_checks_origin() {
+    let config = StationConfig::new("ROCK", DestinationFilter::Deny(vec!["MLBR".to_string()]));
+    let mut app_state = bart_app_state(config);
+    app_state.received_elevators_response(fixtures::json_elevators("There is one elevator out of service at this time: Millbrae Platform."), 0);
+    assert!(app_state.elevator_outages().is_empty());
+}
+
+#[test]
+fn test_elevator_repaired() {
+    let mut app_state = new_app_state();
+    app_state.received_elevators_response(fixtures::json_elevators("There is one elevator out of service at this time: Rockridge Street."), 0);
+    assert_eq!(app_state.elevators_url(0), None);
+    app_state.received_elevators_response(fixtures::json_elevators("There are no elevators out of service at this time."), FIFTEEN_MIN_MICRO);
+    assert!(app_state.elevator_outages().is_empty());
+}
+
+static HEAVY_DELAY_COLOR: RGB8 = RGB8 {r: 16, g: 4, b: 0};
+
+#[test]
+fn test_on_time_train() {
+    let mut app_state = new_app_state();
+    //Under a minute late still counts as on time
+    app_state.received_http_response(fixtures::json_with_estimates(&[Estimate { delay: "59", ..Estimate::new("6") }, Estimate::new("18")]), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<6>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    fill_outer_ring::<18>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_delayed_train() {
+    let mut app_state = new_app_state();
+    //Scheduled for 3 and 15 min, running 4 min (240 sec) and 2 min (150 sec) late
+    app_state.received_http_response(fixtures::json_with_estimates(&[Estimate { delay: "240", ..Estimate::new("7") }, Estimate { delay: "150", ..Estimate::new("17") }]), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<7>(&mut expected_buffer, DELAY_COLOR);
+    fill_inner_ring::<3>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    fill_outer_ring::<17>(&mut expected_buffer, DELAY_COLOR);
+    fill_outer_ring::<15>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_delayed_train_counts_down() {
+    let mut app_state = new_app_state();
+    app_state.received_http_response(fixtures::json_with_estimates(&[Estimate { delay: "240", ..Estimate::new("7") }, Estimate { delay: "150", ..Estimate::new("17") }]), 0);
+    //The delay stays, the scheduled time moves toward now with the train
+    let two_min_micro = 1000000 * 60 * 2;
+    let led_buffer = app_state.get_current_led_buffer(two_min_micro);
+
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<5>(&mut expected_buffer, DELAY_COLOR);
+    fill_inner_ring::<1>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    fill_outer_ring::<15>(&mut expected_buffer, DELAY_COLOR);
+    fill_outer_ring::<13>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_heavily_delayed_train() {
+    let mut app_state = new_app_state();
+    //12 min late, it was due 4 minutes ago so every LED is late
+    app_state.received_http_response(fixtures::json_with_estimates(&[Estimate { delay: "720", ..Estimate::new("8") }, Estimate { delay: "660", ..Estimate::new("20") }]), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<8>(&mut expected_buffer, HEAVY_DELAY_COLOR);
+    fill_outer_ring::<20>(&mut expected_buffer, HEAVY_DELAY_COLOR);
+    fill_outer_ring::<9>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+//Half a minute into the last LED's minute
+static HALF_RED_LINE_COLOR: RGB8 = RGB8 {r: 8, g: 0, b: 0};
+
+static CANCELLED_COLOR: RGB8 = RGB8 {r: 16, g: 16, b: 0};
+
+#[test]
+fn test_cancelled_train_not_on_rings() {
+    let mut app_state = new_app_state();
+    app_state.received_http_response(fixtures::json_with_red_line_estimates(&[Estimate { cancelflag: "1", ..Estimate::new("4") }, Estimate::new("15")]), 0);
+    let states: Vec<DepartureState> = app_state.departures.iter().map(|departure| departure.state).collect();
+    assert_eq!(states, [DepartureState::Minutes(15), DepartureState::Cancelled]);
+
+    let led_buffer = app_state.get_current_led_buffer(0);
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<15>(&mut expected_buffer, RED_LINE_COLOR);
+    expected_buffer[40..].clone_from_slice(&[CANCELLED_COLOR; 4]);
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_cancelled_indicator_flashes() {
+    let mut app_state = new_app_state();
+    app_state.received_http_response(fixtures::json_with_red_line_estimates(&[Estimate { cancelflag: "1", ..Estimate::new("4") }, Estimate::new("15")]), 0);
+    let led_buffer = app_state.get_current_led_buffer(250000);
+    assert_eq!(led_buffer.rgb_buffer[40..], [colors::BLACK; 4]);
+    let led_buffer = app_state.get_current_led_buffer(500000);
+    //Yellow, a red center would read as a configuration error
+    assert_eq!(led_buffer.rgb_buffer[40..], [CANCELLED_COLOR; 4]);
+}
+
+#[test]
+fn test_cancelled_indicator_is_brief() {
+    let mut app_state = new_app_state();
+    app_state.received_http_response(fixtures::json_with_red_line_estimates(&[Estimate { cancelflag: "1", ..Estimate::new("4") }, Estimate::new("15")]), 0);
+    let thirty_sec_micro = 1000000 * 30;
+    let led_buffer = app_state.get_current_led_buffer(thirty_sec_micro);
+
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<14>(&mut expected_buffer, RED_LINE_COLOR);
+    expected_buffer[38] = HALF_RED_LINE_COLOR;
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_cancelled_train_ignored_for_next_fetch() {
+    let mut app_state = new_app_state();
+    //Only one train is actually coming
+    let next_fetch_sec = app_state.received_http_response(fixtures::json_with_red_line_estimates(&[Estimate { cancelflag: "1", ..Estimate::new("4") }, Estimate::new("15")]), 0);
+    assert_eq!(next_fetch_sec, 5 * 60);
+}
+
+#[test]
+fn test_route_colors() {
+    let mut app_state = bart_app_state(StationConfig::new("ROCK", DestinationFilter::All));
+    app_state.received_http_response(fixtures::json_with_red_line_estimates(&[Estimate { cancelflag: "1", ..Estimate::new("5") }, Estimate::new("9")]), 0);
+    let led_buffer = app_state.get_current_led_buffer(1000000 * 30);
+
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<8>(&mut expected_buffer, RED_LINE_COLOR);
+    expected_buffer[32] = HALF_RED_LINE_COLOR;
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_route_palette_override() {
+    //Airport trains in cyan, everything else on the yellow line in white
+    let mut app_state = bart_app_state(StationConfig::new("ROCK", DestinationFilter::All));
+    let palette = "SFIA=#00ffff,YELLOW=#ffffff".parse().unwrap();
+    app_state.set_display_config(DisplayConfig { palette, ..DisplayConfig::default() });
+    app_state.received_http_response(fixtures::json_with_etd_3_trains("4", "15"), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+
+    //SFIA at 4 min, then PITT at 11 min running 6 min late
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<4>(&mut expected_buffer, RGB8 {r: 0, g: 16, b: 16});
+    fill_outer_ring::<11>(&mut expected_buffer, DELAY_COLOR);
+    fill_outer_ring::<5>(&mut expected_buffer, LED_COLOR);
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+static BIKE_MARKER_COLOR: RGB8 = RGB8 {r: 0, g: 8, b: 0};
+static SHORT_TRAIN_COLOR: RGB8 = RGB8 {r: 8, g: 8, b: 1};
+
+#[test]
+fn test_train_details_off_by_default() {
+    let mut app_state = new_app_state();
+    app_state.received_http_response(fixtures::json_with_estimates(&[Estimate { length: "8", ..Estimate::new("4") }, Estimate::new("15")]), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<4>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    fill_outer_ring::<15>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_bike_marker() {
+    let mut app_state = app_state_with(DisplayConfig { train_details: true, ..DisplayConfig::default() });
+    //Only the second train takes bikes
+    app_state.received_http_response(fixtures::json_with_estimates(&[Estimate { bikeflag: "0", ..Estimate::new("4") }, Estimate::new("15")]), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<4>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    fill_outer_ring::<15>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    expected_buffer[15] = BIKE_MARKER_COLOR;
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_short_train_dimmed() {
+    let mut app_state = app_state_with(DisplayConfig { train_details: true, ..DisplayConfig::default() });
+    app_state.received_http_response(fixtures::json_with_estimates(&[Estimate { length: "8", ..Estimate::new("4") }, Estimate { bikeflag: "0", ..Estimate::new("15") }]), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<4>(&mut expected_buffer, SHORT_TRAIN_COLOR);
+    expected_buffer[28] = BIKE_MARKER_COLOR;
+    fill_outer_ring::<15>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_bike_marker_past_full_ring() {
+    let mut app_state = app_state_with(DisplayConfig { train_details: true, ..DisplayConfig::default() });
+    //16 min fills the inner ring, there's no LED left for the marker
+    app_state.received_http_response(fixtures::json_with_estimates(&[Estimate::new("16"), Estimate::new("24")]), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<16>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    fill_outer_ring::<24>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_split_by_direction() {
+    let mut app_state = app_state_with(DisplayConfig {
+        mode: DisplayMode::Split { inner: RingFilter::Direction(Direction::North), outer: RingFilter::Direction(Direction::South) },
+        ..DisplayConfig::default()
+    });
+    app_state.received_http_response(fixtures::json_with_etd_3_trains("4", "15"), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+
+    //Northbound Pittsburg/Bay Point in 11 min (6 min late) inside, southbound SF Airport in 4 min outside
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<11>(&mut expected_buffer, DELAY_COLOR);
+    fill_inner_ring::<5>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    fill_outer_ring::<4>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_split_by_platform() {
+    let mut app_state = app_state_with(DisplayConfig {
+        mode: DisplayMode::Split { inner: RingFilter::Platform(2), outer: RingFilter::Platform(1) },
+        ..DisplayConfig::default()
+    });
+    app_state.received_http_response(fixtures::json_with_etd_3_trains("4", "15"), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<4>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    fill_outer_ring::<11>(&mut expected_buffer, DELAY_COLOR);
+    fill_outer_ring::<5>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_split_leaving_blinks_its_own_ring() {
+    let mut app_state = app_state_with(DisplayConfig {
+        mode: DisplayMode::Split { inner: RingFilter::Direction(Direction::North), outer: RingFilter::Direction(Direction::South) },
+        ..DisplayConfig::default()
+    });
+    app_state.received_http_response(fixtures::json_with_etd_3_trains("Leaving", "15"), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+
+    //The leaving SF Airport train blinks on the outer ring while it counts down to the next one
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<11>(&mut expected_buffer, DELAY_COLOR);
+    fill_inner_ring::<5>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    fill_outer_ring::<15>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+
+    let led_buffer = app_state.get_current_led_buffer(500000);
+    assert_eq!(led_buffer.rgb_buffer[0], colors::BLACK);
+    assert_eq!(led_buffer.rgb_buffer[24], YELLOW_LINE_COLOR);
+}
+
+#[test]
+fn test_split_empty_ring() {
+    let mut app_state = app_state_with(DisplayConfig {
+        mode: DisplayMode::Split { inner: RingFilter::Direction(Direction::North), outer: RingFilter::Direction(Direction::South) },
+        ..DisplayConfig::default()
+    });
+    app_state.received_http_response(fixtures::json_with_estimates(&[Estimate::new("4"), Estimate::new("15")]), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_outer_ring::<4>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_parse_ring_filter() {
+    assert_eq!("North".parse::<RingFilter>(), Ok(RingFilter::Direction(Direction::North)));
+    assert_eq!(" outbound ".parse::<RingFilter>(), Ok(RingFilter::Direction(Direction::Outbound)));
+    assert_eq!("platform:2".parse::<RingFilter>(), Ok(RingFilter::Platform(2)));
+    assert!("platform:two".parse::<RingFilter>().is_err());
+    assert!("east".parse::<RingFilter>().is_err());
+}
+
+static SECOND_LAP_COLOR: RGB8 = RGB8 {r: 8, g: 0, b: 8};
+
+#[test]
+fn test_overflow_truncated_by_default() {
+    let mut app_state = new_app_state();
+    app_state.received_http_response(fixtures::json_with_estimates(&[Estimate::new("30"), Estimate::new("50")]), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_outer_ring::<24>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_overflow_wraps_past_24() {
+    let mut app_state = app_state_with(DisplayConfig { overflow: OverflowStrategy::Wrap, ..DisplayConfig::default() });
+    app_state.received_http_response(fixtures::json_with_estimates(&[Estimate::new("30"), Estimate::new("50")]), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_outer_ring::<24>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    fill_outer_ring::<6>(&mut expected_buffer, SECOND_LAP_COLOR);
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_overflow_wrap_fills_second_lap_at_48() {
+    let mut app_state = app_state_with(DisplayConfig { overflow: OverflowStrategy::Wrap, ..DisplayConfig::default() });
+    app_state.received_http_response(fixtures::json_with_estimates(&[Estimate::new("48"), Estimate::new("50")]), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_outer_ring::<24>(&mut expected_buffer, SECOND_LAP_COLOR);
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_overflow_wrap_past_48() {
+    let mut app_state = app_state_with(DisplayConfig { overflow: OverflowStrategy::Wrap, ..DisplayConfig::default() });
+    app_state.received_http_response(fixtures::json_with_estimates(&[Estimate::new("49"), Estimate::new("70")]), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+
+    //Past two laps there's nothing more to show than a full second lap
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_outer_ring::<24>(&mut expected_buffer, SECOND_LAP_COLOR);
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+
+    app_state.received_http_response(fixtures::json_with_estimates(&[Estimate::new("65"), Estimate::new("70")]), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_overflow_wrap_counts_down() {
+    let mut app_state = app_state_with(DisplayConfig { overflow: OverflowStrategy::Wrap, ..DisplayConfig::default() });
+    app_state.received_http_response(fixtures::json_with_estimates(&[Estimate::new("30"), Estimate::new("50")]), 0);
+    //7 minutes later the 30 min train is back on its first lap
+    let led_buffer = app_state.get_current_led_buffer(7 * 60 * 1000000);
+
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_outer_ring::<23>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_overflow_wrap_late_train() {
+    let mut app_state = app_state_with(DisplayConfig { overflow: OverflowStrategy::Wrap, ..DisplayConfig::default() });
+    //Due in 30 min, 4 min later than scheduled
+    app_state.received_http_response(fixtures::json_with_estimates(&[Estimate { delay: "240", ..Estimate::new("30") }, Estimate::new("50")]), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_outer_ring::<24>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    fill_outer_ring::<6>(&mut expected_buffer, DELAY_COLOR);
+    fill_outer_ring::<2>(&mut expected_buffer, SECOND_LAP_COLOR);
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_parse_overflow_strategy() {
+    assert_eq!("Wrap".parse::<OverflowStrategy>(), Ok(OverflowStrategy::Wrap));
+    assert_eq!(" truncate".parse::<OverflowStrategy>(), Ok(OverflowStrategy::Truncate));
+    assert!("scale".parse::<OverflowStrategy>().is_err());
+}
+
+#[test]
+fn test_two_minutes_per_led() {
+    let mut app_state = app_state_with(DisplayConfig { time_scale: TimeScale::MinutesPerLed(2), ..DisplayConfig::default() });
+    app_state.received_http_response(fixtures::json_with_estimates(&[Estimate::new("4"), Estimate::new("15")]), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+
+    //15 min is seven and a half LEDs, the eighth at half brightness
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<2>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    fill_outer_ring::<7>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    expected_buffer[7] = RGB8 {r: 8, g: 8, b: 1};
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_time_scale_fits_inner_ring() {
+    let mut app_state = app_state_with(DisplayConfig { time_scale: TimeScale::MinutesPerLed(2), ..DisplayConfig::default() });
+    //20 min would spill off the inner ring at one minute per LED
+    app_state.received_http_response(fixtures::json_with_estimates(&[Estimate::new("20"), Estimate::new("40")]), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<10>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    fill_outer_ring::<20>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_log_time_scale() {
+    let mut app_state = app_state_with(DisplayConfig { time_scale: TimeScale::Log, ..DisplayConfig::default() });
+    app_state.received_http_response(fixtures::json_with_estimates(&[Estimate::new("4"), Estimate::new("15")]), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+
+    //4 min is 6.26 of 16 LEDs and 15 min is 16.19 of 24 LEDs, an hour would fill either ring
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<6>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    expected_buffer[30] = RGB8 {r: 4, g: 4, b: 0};
+    fill_outer_ring::<16>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    expected_buffer[16] = RGB8 {r: 3, g: 3, b: 0};
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_time_scale_late_train() {
+    let mut app_state = app_state_with(DisplayConfig { time_scale: TimeScale::MinutesPerLed(2), ..DisplayConfig::default() });
+    //Due in 14 min, 4 min later than scheduled
+    app_state.received_http_response(fixtures::json_with_estimates(&[Estimate { delay: "240", ..Estimate::new("14") }, Estimate::new("30")]), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<7>(&mut expected_buffer, DELAY_COLOR);
+    fill_inner_ring::<5>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    fill_outer_ring::<15>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_time_scale_leds() {
+    assert_eq!(TimeScale::Linear.leds(7.5, 24), 7.5);
+    assert_eq!(TimeScale::MinutesPerLed(3).leds(7.5, 24), 2.5);
+    assert_eq!(TimeScale::Log.leds(60.0, 16), 16.0);
+    assert_eq!(TimeScale::Log.leds(-1.0, 16), 0.0);
+}
+
+#[test]
+fn test_parse_time_scale() {
+    assert_eq!("Log".parse::<TimeScale>(), Ok(TimeScale::Log));
+    assert_eq!(" 2".parse::<TimeScale>(), Ok(TimeScale::MinutesPerLed(2)));
+    assert_eq!("1".parse::<TimeScale>(), Ok(TimeScale::Linear));
+    assert!("0".parse::<TimeScale>().is_err());
+    assert!("fast".parse::<TimeScale>().is_err());
+}
+
+//Half a minute into the last LED's minute
+static HALF_YELLOW_LINE_COLOR: RGB8 = RGB8 {r: 8, g: 8, b: 1};
+//Thursday 2024-05-09 16:24:03 PDT, the fixtures' BART response time
+static FIXTURE_RESPONSE_UNIX_SEC: u64 = 1715297043;
+
+#[test]
+fn test_last_led_fades_over_the_minute() {
+    let mut app_state = new_app_state();
+    app_state.received_http_response(fixtures::json_with_estimates(&[Estimate::new("4"), Estimate::new("15")]), 0);
+    let fifteen_sec_micro = 1000000 * 15;
+
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<3>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    fill_outer_ring::<14>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    expected_buffer[27] = RGB8 {r: 12, g: 12, b: 2};
+    expected_buffer[14] = RGB8 {r: 12, g: 12, b: 2};
+    assert_eq!(expected_buffer, app_state.get_current_led_buffer(fifteen_sec_micro).rgb_buffer);
+
+    expected_buffer[27] = HALF_YELLOW_LINE_COLOR;
+    expected_buffer[14] = HALF_YELLOW_LINE_COLOR;
+    assert_eq!(expected_buffer, app_state.get_current_led_buffer(fifteen_sec_micro * 2).rgb_buffer);
+
+    expected_buffer[27] = RGB8 {r: 4, g: 4, b: 0};
+    expected_buffer[14] = RGB8 {r: 4, g: 4, b: 0};
+    assert_eq!(expected_buffer, app_state.get_current_led_buffer(fifteen_sec_micro * 3).rgb_buffer);
+
+    //A whole minute later the LED is gone
+    expected_buffer[27] = colors::BLACK;
+    expected_buffer[14] = colors::BLACK;
+    assert_eq!(expected_buffer, app_state.get_current_led_buffer(fifteen_sec_micro * 4).rgb_buffer);
+}
+
+#[test]
+fn test_countdown_starts_at_response_time() {
+    let mut app_state = new_app_state();
+    //The response was built 30 sec before it arrived
+    app_state.time_synced(Duration::from_secs(FIXTURE_RESPONSE_UNIX_SEC + 30), 0);
+    app_state.received_http_response(fixtures::json_with_estimates(&[Estimate::new("4"), Estimate::new("15")]), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<3>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    expected_buffer[27] = HALF_YELLOW_LINE_COLOR;
+    fill_outer_ring::<14>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    expected_buffer[14] = HALF_YELLOW_LINE_COLOR;
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_response_time_ignored_when_clocks_disagree() {
+    let mut app_state = new_app_state();
+    //Ten minutes is more than any response sits around, the device clock must be off
+    app_state.time_synced(Duration::from_secs(FIXTURE_RESPONSE_UNIX_SEC + 600), 0);
+    app_state.received_http_response(fixtures::json_with_estimates(&[Estimate::new("4"), Estimate::new("15")]), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<4>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    fill_outer_ring::<15>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_walk_time_subtracted() {
+    let mut app_state = app_state_with(DisplayConfig { walk_time: WalkTime::new(7, 0), ..DisplayConfig::default() });
+    app_state.received_http_response(fixtures::json_with_estimates(&[Estimate::new("10"), Estimate::new("20")]), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+
+    //Leave in 3 and 13 min
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<3>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    fill_outer_ring::<13>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_walk_time_hides_uncatchable_train() {
+    let mut app_state = app_state_with(DisplayConfig { walk_time: WalkTime::new(7, 0), ..DisplayConfig::default() });
+    //Exactly the walk time away, it leaves as we get there
+    app_state.received_http_response(fixtures::json_with_estimates(&[Estimate::new("7"), Estimate::new("20")]), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<13>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_walk_time_train_becomes_uncatchable() {
+    let mut app_state = app_state_with(DisplayConfig { walk_time: WalkTime::new(7, 0), ..DisplayConfig::default() });
+    app_state.received_http_response(fixtures::json_with_estimates(&[Estimate::new("8"), Estimate::new("20")]), 0);
+
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    expected_buffer[24] = HALF_YELLOW_LINE_COLOR;
+    fill_outer_ring::<12>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    expected_buffer[12] = HALF_YELLOW_LINE_COLOR;
+    assert_eq!(expected_buffer, app_state.get_current_led_buffer(1000000 * 30).rgb_buffer);
+
+    //A minute in, the first train is out of reach and the second moves inside
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<12>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    assert_eq!(expected_buffer, app_state.get_current_led_buffer(1000000 * 60).rgb_buffer);
+}
+
+#[test]
+fn test_run_now_window() {
+    let mut app_state = app_state_with(DisplayConfig { walk_time: WalkTime::new(7, 2), ..DisplayConfig::default() });
+    //One minute short of the walk, running still makes it
+    app_state.received_http_response(fixtures::json_with_estimates(&[Estimate::new("6"), Estimate::new("20")]), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_outer_ring::<13>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    expected_buffer[24] = YELLOW_LINE_COLOR;
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+
+    let led_buffer = app_state.get_current_led_buffer(500000);
+    assert_eq!(led_buffer.rgb_buffer[24], colors::BLACK);
+}
+
+#[test]
+fn test_run_now_window_closes() {
+    let mut app_state = app_state_with(DisplayConfig { walk_time: WalkTime::new(7, 2), ..DisplayConfig::default() });
+    //Two minutes short of the walk is too late even running
+    app_state.received_http_response(fixtures::json_with_estimates(&[Estimate::new("5"), Estimate::new("20")]), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<13>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_walk_time_hides_leaving_train() {
+    let mut app_state = app_state_with(DisplayConfig { walk_time: WalkTime::new(3, 0), ..DisplayConfig::default() });
+    app_state.received_http_response(fixtures::json_with_estimates(&[Estimate::new("Leaving"), Estimate::new("15")]), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<12>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+//Bottom of the urgency pulse, a quarter brightness
+static PULSE_DIM_YELLOW_LINE_COLOR: RGB8 = RGB8 {r: 4, g: 4, b: 0};
+
+#[test]
+fn test_urgency_pulses_inner_ring() {
+    let mut app_state = app_state_with(DisplayConfig { walk_time: WalkTime::default(), leave_now_min: 3, ..DisplayConfig::default() });
+    app_state.received_http_response(fixtures::json_with_estimates(&[Estimate::new("3"), Estimate::new("15")]), 0);
+
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<3>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    fill_outer_ring::<15>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    assert_eq!(expected_buffer, app_state.get_current_led_buffer(0).rgb_buffer);
+
+    //Three minutes out pulses once a second, only the inner ring
+    fill_inner_ring::<3>(&mut expected_buffer, PULSE_DIM_YELLOW_LINE_COLOR);
+    assert_eq!(expected_buffer, app_state.get_current_led_buffer(500000).rgb_buffer);
+}
+
+#[test]
+fn test_urgency_pulses_ring_with_train() {
+    let mut app_state = app_state_with(DisplayConfig {
+        mode: DisplayMode::Split { inner: RingFilter::Direction(Direction::North), outer: RingFilter::Direction(Direction::South) },
+        leave_now_min: 4,
+        ..DisplayConfig::default()
+    });
+    //Only southbound SF Airport in 4 min on the outer ring is close enough to hurry for
+    app_state.received_http_response(fixtures::json_with_etd_3_trains("4", "15"), 0);
+
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<11>(&mut expected_buffer, DELAY_COLOR);
+    fill_inner_ring::<5>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    fill_outer_ring::<4>(&mut expected_buffer, PULSE_DIM_YELLOW_LINE_COLOR);
+    assert_eq!(expected_buffer, app_state.get_current_led_buffer(625000).rgb_buffer);
+}
+
+#[test]
+fn test_urgency_escalates() {
+    let mut app_state = app_state_with(DisplayConfig { walk_time: WalkTime::default(), leave_now_min: 3, ..DisplayConfig::default() });
+    app_state.received_http_response(fixtures::json_with_estimates(&[Estimate::new("1"), Estimate::new("15")]), 0);
+
+    //One minute out pulses twice a second
+    let led_buffer = app_state.get_current_led_buffer(250000);
+    assert_eq!(led_buffer.rgb_buffer[24], PULSE_DIM_YELLOW_LINE_COLOR);
+    let led_buffer = app_state.get_current_led_buffer(500000);
+    assert_eq!(led_buffer.rgb_buffer[24], YELLOW_LINE_COLOR);
+}
+
+#[test]
+fn test_urgency_outside_window() {
+    let mut app_state = app_state_with(DisplayConfig { walk_time: WalkTime::default(), leave_now_min: 3, ..DisplayConfig::default() });
+    app_state.received_http_response(fixtures::json_with_estimates(&[Estimate::new("4"), Estimate::new("15")]), 0);
+    let led_buffer = app_state.get_current_led_buffer(500000);
+
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<4>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    fill_outer_ring::<15>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_urgency_when_running() {
+    let mut app_state = app_state_with(DisplayConfig { walk_time: WalkTime::new(7, 2), leave_now_min: 2, ..DisplayConfig::default() });
+    //Only catchable running, the blinking first LED pulses four times a second
+    app_state.received_http_response(fixtures::json_with_estimates(&[Estimate::new("6"), Estimate::new("20")]), 0);
+    assert_eq!(app_state.get_current_led_buffer(0).rgb_buffer[24], YELLOW_LINE_COLOR);
+    assert_eq!(app_state.get_current_led_buffer(125000).rgb_buffer[24], PULSE_DIM_YELLOW_LINE_COLOR);
+    assert_eq!(app_state.get_current_led_buffer(250000).rgb_buffer[24], YELLOW_LINE_COLOR);
+}
+
+#[test]
+fn test_urgency_off_by_default() {
+    let mut app_state = new_app_state();
+    app_state.received_http_response(fixtures::json_with_estimates(&[Estimate::new("1"), Estimate::new("15")]), 0);
+    let led_buffer = app_state.get_current_led_buffer(250000);
+    assert_eq!(led_buffer.rgb_buffer[24], YELLOW_LINE_COLOR);
+}
+
+static LATER_TICK_COLOR: RGB8 = RGB8 {r: 4, g: 4, b: 0};
+
+#[test]
+fn test_timeline_ticks_every_train() {
+    let mut app_state = app_state_with(DisplayConfig { mode: DisplayMode::Timeline, ..DisplayConfig::default() });
+    //4, 11, 15, 21 and 23 min are on the timeline, 26, 35, 45 and 55 past it
+    app_state.received_http_response(fixtures::json_with_etd_3_trains("4", "15"), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<4>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    for tick in [3, 10, 14, 20, 22] {
+        expected_buffer[tick] = YELLOW_LINE_COLOR;
+    }
+    expected_buffer[23] = LATER_TICK_COLOR;
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_timeline_bunched_trains() {
+    let mut app_state = app_state_with(DisplayConfig { mode: DisplayMode::Timeline, ..DisplayConfig::default() });
+    //Airport and Pittsburg/Bay Point trains both 11 min out
+    app_state.received_http_response(fixtures::json_with_etd_3_trains("4", "11"), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<4>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    for tick in [3, 20, 22] {
+        expected_buffer[tick] = YELLOW_LINE_COLOR;
+    }
+    expected_buffer[10] = LED_COLOR;
+    expected_buffer[23] = LATER_TICK_COLOR;
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_timeline_past_the_ring() {
+    let mut app_state = app_state_with(DisplayConfig { mode: DisplayMode::Timeline, ..DisplayConfig::default() });
+    app_state.received_http_response(fixtures::json_with_estimates(&[Estimate::new("24"), Estimate::new("40")]), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+
+    //24 min still ticks the last LED, so there's no room to say 40 min is coming too
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<16>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    expected_buffer[23] = YELLOW_LINE_COLOR;
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+
+    app_state.received_http_response(fixtures::json_with_estimates(&[Estimate::new("30"), Estimate::new("40")]), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+    expected_buffer[23] = LATER_TICK_COLOR;
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_timeline_ticks_move() {
+    let mut app_state = app_state_with(DisplayConfig { mode: DisplayMode::Timeline, ..DisplayConfig::default() });
+    app_state.received_http_response(fixtures::json_with_estimates(&[Estimate::new("4"), Estimate::new("9")]), 0);
+    let led_buffer = app_state.get_current_led_buffer(1000000 * 60 * 2);
+
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<2>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    expected_buffer[1] = YELLOW_LINE_COLOR;
+    expected_buffer[6] = YELLOW_LINE_COLOR;
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+static ALTERNATE_GAP_COLOR: RGB8 = RGB8 {r: 4, g: 4, b: 0};
+
+#[test]
+fn test_headway_gaps() {
+    let mut app_state = app_state_with(DisplayConfig { mode: DisplayMode::Headway, ..DisplayConfig::default() });
+    //4, 11, 15, 21, 23, 26 min... leaves gaps of 7, 4, 6, 2 and 3 min after the first train
+    app_state.received_http_response(fixtures::json_with_etd_3_trains("4", "15"), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<4>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    fill_outer_ring::<24>(&mut expected_buffer, ALTERNATE_GAP_COLOR);
+    fill_outer_ring::<22>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    fill_outer_ring::<19>(&mut expected_buffer, ALTERNATE_GAP_COLOR);
+    fill_outer_ring::<17>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    fill_outer_ring::<11>(&mut expected_buffer, ALTERNATE_GAP_COLOR);
+    fill_outer_ring::<7>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_headway_bunched_trains() {
+    let mut app_state = app_state_with(DisplayConfig { mode: DisplayMode::Headway, ..DisplayConfig::default() });
+    //Two trains 11 min out, then 21, 23 and 26
+    app_state.received_http_response(fixtures::json_with_etd_3_trains("4", "11"), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<4>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    fill_outer_ring::<24>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    fill_outer_ring::<23>(&mut expected_buffer, ALTERNATE_GAP_COLOR);
+    fill_outer_ring::<20>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    fill_outer_ring::<18>(&mut expected_buffer, ALTERNATE_GAP_COLOR);
+    fill_outer_ring::<7>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    expected_buffer[7] = LED_COLOR;
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_headway_single_gap() {
+    let mut app_state = app_state_with(DisplayConfig { mode: DisplayMode::Headway, ..DisplayConfig::default() });
+    app_state.received_http_response(fixtures::json_with_estimates(&[Estimate::new("4"), Estimate::new("6")]), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<4>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    fill_outer_ring::<2>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_set_display_mode() {
+    let mut app_state = new_app_state();
+    assert_eq!(app_state.display_mode(), &DisplayMode::NextTwo);
+    app_state.set_display_mode(DisplayMode::Headway);
+    assert_eq!(app_state.display_mode(), &DisplayMode::Headway);
+
+    //A lone train has no gap to show
+    app_state.received_http_response(fixtures::json_with_etd_1_train("4"), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+    assert_eq!(led_buffer.rgb_buffer[..24], [colors::BLACK; 24]);
+}
+
+static CLOCK_COLOR: RGB8 = RGB8 {r: 16, g: 16, b: 16};
+
+#[test]
+fn test_clock_without_departures() {
+    let mut app_state = new_app_state();
+    app_state.time_synced(Duration::from_secs(WEDNESDAY_MORNING_UNIX_SEC), 0);
+    app_state.received_http_response(fixtures::json_no_service(), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+
+    //07:15, the hour hand 9.67 LEDs around and the minute hand on the sixth 2.5 min LED
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    expected_buffer[24 + 9] = CLOCK_COLOR;
+    expected_buffer[6] = CLOCK_COLOR;
+    expected_buffer[40..].clone_from_slice(&[NO_SERVICE_COLOR; 4]);
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+#[test]
+fn test_clock_with_only_cancelled_trains() {
+    let mut app_state = new_app_state();
+    app_state.time_synced(Duration::from_secs(WEDNESDAY_MORNING_UNIX_SEC), 0);
+    app_state.received_http_response(fixtures::json_with_red_line_estimates(&[Estimate { cancelflag: "1", ..Estimate::new("6") }]), 0);
+    //A cancelled train draws nothing, so the rings show 07:15
+    let led_buffer = app_state.get_current_led_buffer(0);
+    assert_eq!(&led_buffer.rgb_buffer[..24], &expected_hand::<24>(6));
+    assert_eq!(&led_buffer.rgb_buffer[24..40], &expected_hand::<16>(9));
+}
+
+#[test]
+fn test_clock_after_last_train_leaves() {
+    let mut app_state = new_app_state();
+    app_state.time_synced(Duration::from_secs(WEDNESDAY_MORNING_UNIX_SEC), 0);
+    app_state.received_http_response(fixtures::json_with_etd_1_train("4"), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+    assert_eq!(&led_buffer.rgb_buffer[..24], &[colors::BLACK; 24]);
+    //07:20, the train left a minute ago and nothing has been fetched since
+    let led_buffer = app_state.get_current_led_buffer(1000000 * 60 * 5);
+    assert_eq!(&led_buffer.rgb_buffer[..24], &expected_hand::<24>(8));
+    assert_eq!(&led_buffer.rgb_buffer[24..40], &expected_hand::<16>(9));
+}
+
+#[test]
+fn test_clock_keeps_time() {
+    let mut app_state = new_app_state();
+    app_state.time_synced(Duration::from_secs(WEDNESDAY_MORNING_UNIX_SEC), 0);
+    app_state.received_http_response(fixtures::json_no_service(), 0);
+    //08:05
+    let led_buffer = app_state.get_current_led_buffer(1000000 * 60 * 50);
+    assert_eq!(&led_buffer.rgb_buffer[..24], &expected_hand::<24>(2));
+    assert_eq!(&led_buffer.rgb_buffer[24..40], &expected_hand::<16>(10));
+    //08:59
+    let led_buffer = app_state.get_current_led_buffer(1000000 * 60 * 104);
+    assert_eq!(&led_buffer.rgb_buffer[..24], &expected_hand::<24>(23));
+    assert_eq!(&led_buffer.rgb_buffer[24..40], &expected_hand::<16>(11));
+}
+
+#[test]
+fn test_clock_before_first_fetch() {
+    let mut app_state = new_app_state();
+    //Saturday 00:10, midnight is the top of the inner ring
+    app_state.time_synced(Duration::from_secs(SATURDAY_AFTER_MIDNIGHT_UNIX_SEC), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+    assert_eq!(&led_buffer.rgb_buffer[..24], &expected_hand::<24>(4));
+    assert_eq!(&led_buffer.rgb_buffer[24..40], &expected_hand::<16>(0));
+}
+
+#[test]
+fn test_no_clock_without_synced_time() {
+    let mut app_state = new_app_state();
+    app_state.received_http_response(fixtures::json_no_service(), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+    assert_eq!(led_buffer.rgb_buffer[..40], [colors::BLACK; 40]);
+}
+
+#[test]
+fn test_no_clock_with_departures() {
+    let mut app_state = new_app_state();
+    app_state.time_synced(Duration::from_secs(WEDNESDAY_MORNING_UNIX_SEC), 0);
+    app_state.received_http_response(fixtures::json_with_estimates(&[Estimate::new("4"), Estimate::new("15")]), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+
+    let mut expected_buffer: [RGB8; 44] = [colors::BLACK; 44];
+    fill_inner_ring::<4>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    fill_outer_ring::<15>(&mut expected_buffer, YELLOW_LINE_COLOR);
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
+
+fn expected_hand<const N: usize>(position: usize) -> [RGB8; N] {
+    let mut ring = [colors::BLACK; N];
+    ring[position] = CLOCK_COLOR;
+    ring
+}
+
+fn fill_outer_ring<const N: usize>(buffer: &mut [RGB8; 44], color: RGB8) {
+    buffer[..N].clone_from_slice(&[color; N]);
+}
+
+fn fill_inner_ring<const N: usize>(buffer: &mut [RGB8; 44], color: RGB8) {
+    buffer[24..24+N].clone_from_slice(&[color; N]);
+}
+
+fn new_app_state() -> AppState {
+    let config = StationConfig::new("ROCK", DestinationFilter::Allow(vec!["MLBR".to_string(), "SFIA".to_string()]));
+    bart_app_state(config)
+}
+
+fn bart_app_state(config: StationConfig) -> AppState {
+    AppState::new(launch_time(), Box::new(BartProvider::new("MW9S-E7SL-26DU-VV8V", config)))
+}
+
+fn launch_time() -> Duration {
+    Duration::new(0, 0)
+} 
+
+fn weekday_timetable() -> StaticTimetable {
+    StaticTimetable::new(WEEK_SCHEDULE, &SCHEDULE_DESTINATIONS, [Direction::South, Direction::North])
+}
+
+/// Clock synced at boot so that `unix_sec` is 15 minutes after launch
+fn scheduled_app_state(unix_sec: u64) -> AppState {
+    let mut app_state = new_app_state();
+    app_state.set_static_timetable(weekday_timetable());
+    app_state.time_synced(Duration::from_secs(unix_sec - 15 * 60), 0);
+    app_state
+}
+
+fn early_morning_app_state() -> AppState {
+    let mut app_state = new_app_state();
+    app_state.time_synced(Duration::from_secs(BEFORE_FIRST_TRAIN_UNIX_SEC), 0);
+    app_state
+}
+
+fn app_state_with(display_config: DisplayConfig) -> AppState {
+    let mut app_state = bart_app_state(StationConfig::new("ROCK", DestinationFilter::All));
+    app_state.set_display_config(display_config);
+    app_state
+}
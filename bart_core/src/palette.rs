@@ -0,0 +1,90 @@
+use smart_leds::RGB8;
+use smart_leds::colors;
+use std::str::FromStr;
+
+use crate::Departure;
+#[cfg(test)]
+#[path = "palette.test.rs"]
+mod tests;
+
+/// Flashes in the center when a train is cancelled. Not red, a full red center is a configuration error
+pub const CANCELLED_COLOR: RGB8 = colors::YELLOW;
+
+//BART's line colors, for feeds that name the color without sending a hexcolor
+const LINE_COLORS: [(&str, RGB8); 7] = [
+    ("RED", RGB8 { r: 0xff, g: 0x00, b: 0x00 }),
+    ("ORANGE", RGB8 { r: 0xff, g: 0x99, b: 0x33 }),
+    ("YELLOW", RGB8 { r: 0xff, g: 0xff, b: 0x33 }),
+    ("GREEN", RGB8 { r: 0x33, g: 0x99, b: 0x33 }),
+    ("BLUE", RGB8 { r: 0x00, g: 0x99, b: 0xcc }),
+    ("BEIGE", RGB8 { r: 0xd5, g: 0xcf, b: 0xa3 }),
+    ("WHITE", RGB8 { r: 0xff, g: 0xff, b: 0xff }),
+];
+
+/// Picks the color a train is drawn in.
+/// Overrides are keyed by destination abbreviation or line color name, an abbreviation wins over a line.
+/// Without an override the API's hexcolor is used, then the built-in line table, then white.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RoutePalette {
+    overrides: Vec<(String, RGB8)>,
+}
+
+impl RoutePalette {
+    pub fn new(overrides: Vec<(String, RGB8)>) -> RoutePalette {
+        let overrides = overrides.into_iter().map(|(key, color)| (key.to_ascii_uppercase(), color)).collect();
+        RoutePalette { overrides }
+    }
+
+    pub fn color_for(&self, departure: &Departure) -> RGB8 {
+        self.override_for(&departure.abbreviation)
+            .or_else(|| self.override_for(&departure.color))
+            .or(departure.hexcolor)
+            .or_else(|| line_color(&departure.color))
+            .unwrap_or(colors::WHITE)
+    }
+
+    fn override_for(&self, key: &str) -> Option<RGB8> {
+        if key.is_empty() {
+            return None;
+        }
+        self.overrides.iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(key))
+            .map(|(_, color)| *color)
+    }
+}
+
+/// Parses the config file form, `"SFIA=#00ffff,RED=#ff00ff"`. Entries that don't parse are skipped.
+impl FromStr for RoutePalette {
+    type Err = std::convert::Infallible;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let overrides = value
+            .split(',')
+            .filter_map(|entry| {
+                let (key, color) = entry.split_once('=')?;
+                let key = key.trim();
+                if key.is_empty() {
+                    return None;
+                }
+                Some((key.to_string(), parse_hexcolor(color)?))
+            })
+            .collect();
+        Ok(RoutePalette::new(overrides))
+    }
+}
+
+fn line_color(name: &str) -> Option<RGB8> {
+    LINE_COLORS.iter()
+        .find(|(line, _)| line.eq_ignore_ascii_case(name))
+        .map(|(_, color)| *color)
+}
+
+/// `#ffff33` as an RGB8
+pub(crate) fn parse_hexcolor(value: &str) -> Option<RGB8> {
+    let hex = value.trim().strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let rgb = u32::from_str_radix(hex, 16).ok()?;
+    Some(RGB8 { r: (rgb >> 16) as u8, g: (rgb >> 8) as u8, b: rgb as u8 })
+}
@@ -0,0 +1,51 @@
+use super::*;
+use crate::{DepartureState, Direction, Duration};
+
+static YELLOW_LINE: RGB8 = RGB8 { r: 0xff, g: 0xff, b: 0x33 };
+static CYAN: RGB8 = RGB8 { r: 0x00, g: 0xff, b: 0xff };
+
+fn departure(abbreviation: &str, color: &str, hexcolor: Option<RGB8>) -> Departure {
+    Departure {
+        destination: String::new(),
+        abbreviation: abbreviation.to_string(),
+        state: DepartureState::Minutes(5),
+        platform: 2,
+        direction: Direction::South,
+        car_count: 8,
+        color: color.to_string(),
+        hexcolor,
+        bike_allowed: true,
+        delay: Duration::ZERO,
+    }
+}
+
+#[test]
+fn test_hexcolor_from_api() {
+    let palette = RoutePalette::default();
+    assert_eq!(palette.color_for(&departure("SFIA", "YELLOW", Some(RGB8 { r: 1, g: 2, b: 3 }))), RGB8 { r: 1, g: 2, b: 3 });
+}
+
+#[test]
+fn test_line_color_table() {
+    let palette = RoutePalette::default();
+    assert_eq!(palette.color_for(&departure("SFIA", "yellow", None)), YELLOW_LINE);
+}
+
+#[test]
+fn test_unknown_line_is_white() {
+    let palette = RoutePalette::default();
+    assert_eq!(palette.color_for(&departure("51A", "", None)), colors::WHITE);
+}
+
+#[test]
+fn test_destination_override_wins() {
+    let palette: RoutePalette = "YELLOW=#ff00ff, sfia=#00ffff".parse().unwrap();
+    assert_eq!(palette.color_for(&departure("SFIA", "YELLOW", Some(YELLOW_LINE))), CYAN);
+    assert_eq!(palette.color_for(&departure("PITT", "YELLOW", Some(YELLOW_LINE))), colors::MAGENTA);
+}
+
+#[test]
+fn test_parse_skips_bad_entries() {
+    let palette: RoutePalette = "SFIA=#00ffff,MLBR=red,=#ffffff,PITT".parse().unwrap();
+    assert_eq!(palette, RoutePalette::new(vec![("SFIA".to_string(), CYAN)]));
+}
@@ -0,0 +1,52 @@
+use crate::time::LocalTime;
+use crate::{Advisory, ApiError, ApiNotice, Departure, ElevatorOutage};
+
+/// Everything one fetch told us, in a form that doesn't depend on who we fetched it from.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FeedSnapshot {
+    pub departures: Vec<Departure>,
+    pub notices: Vec<ApiNotice>,
+    /// When the agency built the response, the instant its minutes count down from
+    pub response_unix_sec: Option<i64>,
+}
+
+/// A transit agency's real-time API.
+/// Knows where to fetch departures from and how to turn the response into the ones this device cares about.
+pub trait TransitProvider {
+    fn departures_url(&self) -> String;
+    fn parse_departures(&self, payload: &[u8]) -> Result<FeedSnapshot, ApiError>;
+
+    /// Whether a train to this destination belongs on the rings. The live feed is already filtered while parsing,
+    /// this applies the same filter to the compiled in timetable.
+    fn shows_destination(&self, _abbreviation: &str) -> bool {
+        true
+    }
+
+    /// Where to find the timetable while the real-time feed has nothing to show, `None` when the agency doesn't have one.
+    fn schedule_url(&self) -> Option<String> {
+        None
+    }
+
+    /// Upcoming scheduled departures, soonest first, relative to the local wall clock.
+    fn parse_schedule(&self, _payload: &[u8], _now: LocalTime) -> Result<Vec<Departure>, ApiError> {
+        Ok(Vec::new())
+    }
+
+    /// Service advisories, fetched less often than departures. `None` when the agency doesn't publish any.
+    fn advisories_url(&self) -> Option<String> {
+        None
+    }
+
+    fn parse_advisories(&self, _payload: &[u8]) -> Result<Vec<Advisory>, ApiError> {
+        Ok(Vec::new())
+    }
+
+    /// Elevator status for the stations on the configured trip, on the same cadence as advisories.
+    fn elevators_url(&self) -> Option<String> {
+        None
+    }
+
+    fn parse_elevator_outages(&self, _payload: &[u8]) -> Result<Vec<ElevatorOutage>, ApiError> {
+        Ok(Vec::new())
+    }
+}
@@ -0,0 +1,137 @@
+use serde::Deserialize;
+
+use crate::time::parse_rfc3339;
+use crate::{ApiError, Departure, DepartureState, Direction, Duration, FeedSnapshot, TransitProvider};
+#[cfg(test)]
+#[path = "siri.test.rs"]
+mod tests;
+
+/// Bus and Muni arrivals at one stop from 511.org's SIRI StopMonitoring API.
+pub struct SiriProvider {
+    api_key: String,
+    agency: String,
+    stop_code: String,
+    line_refs: Vec<String>,
+}
+
+impl SiriProvider {
+    /// An empty `line_refs` shows every line serving the stop.
+    pub fn new(api_key: &str, agency: &str, stop_code: &str, line_refs: Vec<String>) -> SiriProvider {
+        SiriProvider { api_key: api_key.to_string(), agency: agency.to_string(), stop_code: stop_code.to_string(), line_refs }
+    }
+
+    fn departure(&self, journey: MonitoredVehicleJourney, response_time: i64) -> Option<Departure> {
+        if !self.line_refs.is_empty() && !self.line_refs.iter().any(|line| line.eq_ignore_ascii_case(&journey.line_ref)) {
+            return None;
+        }
+        let call = journey.monitored_call?;
+        let aimed = call.aimed_arrival_time.as_deref().and_then(parse_rfc3339);
+        let expected = call.expected_arrival_time.as_deref().and_then(parse_rfc3339);
+        //Unmonitored vehicles only have the timetable time
+        let arrival = expected.or(aimed)?;
+        let seconds_until = arrival - response_time;
+        let state = if seconds_until < -SECONDS_PER_MIN {
+            return None;
+        } else if seconds_until < SECONDS_PER_MIN {
+            DepartureState::Leaving
+        } else {
+            DepartureState::Minutes((seconds_until / SECONDS_PER_MIN) as u32)
+        };
+        let delay = match (expected, aimed) {
+            (Some(expected), Some(aimed)) if expected > aimed => Duration::from_secs((expected - aimed) as u64),
+            _ => Duration::ZERO,
+        };
+        let direction = match journey.direction_ref.as_deref().map(str::to_ascii_uppercase).as_deref() {
+            Some("N" | "NB" | "NORTH") => Direction::North,
+            Some("S" | "SB" | "SOUTH") => Direction::South,
+            Some("IB" | "INBOUND") => Direction::Inbound,
+            Some("OB" | "OUTBOUND") => Direction::Outbound,
+            //Split mode shows a bus with no usable direction on both rings
+            _ => Direction::Unknown,
+        };
+        let destination = journey.destination_name
+            .or(call.destination_display)
+            .unwrap_or_else(|| journey.line_ref.clone());
+        Some(Departure {
+            destination,
+            abbreviation: journey.line_ref,
+            state,
+            platform: 0,
+            direction,
+            car_count: 0,
+            color: String::new(),
+            hexcolor: None,
+            bike_allowed: false,
+            delay,
+        })
+    }
+}
+
+const SECONDS_PER_MIN: i64 = 60;
+
+impl TransitProvider for SiriProvider {
+    fn departures_url(&self) -> String {
+        format!("https://api.511.org/transit/StopMonitoring?api_key={}&agency={}&stopCode={}&format=json", self.api_key, self.agency, self.stop_code)
+    }
+
+    fn parse_departures(&self, payload: &[u8]) -> Result<FeedSnapshot, ApiError> {
+        //511 starts its JSON with a UTF-8 byte order mark
+        let payload = payload.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(payload);
+        let json: Top = serde_json::from_slice(payload)?;
+        let delivery = json.service_delivery.stop_monitoring_delivery;
+        let response_time = json.service_delivery.response_timestamp
+            .as_deref()
+            .or(delivery.response_timestamp.as_deref())
+            .and_then(parse_rfc3339)
+            .ok_or_else(|| ApiError::Schema("missing ResponseTimestamp".to_string()))?;
+        let departures = delivery.monitored_stop_visit
+            .into_iter()
+            .filter_map(|visit| self.departure(visit.monitored_vehicle_journey, response_time))
+            .collect();
+        Ok(FeedSnapshot { departures, notices: Vec::new(), response_unix_sec: Some(response_time) })
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct Top {
+    service_delivery: ServiceDelivery
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct ServiceDelivery {
+    response_timestamp: Option<String>,
+    stop_monitoring_delivery: StopMonitoringDelivery
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct StopMonitoringDelivery {
+    response_timestamp: Option<String>,
+    #[serde(default)]
+    monitored_stop_visit: Vec<MonitoredStopVisit>
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct MonitoredStopVisit {
+    monitored_vehicle_journey: MonitoredVehicleJourney
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct MonitoredVehicleJourney {
+    line_ref: String,
+    direction_ref: Option<String>,
+    destination_name: Option<String>,
+    monitored_call: Option<MonitoredCall>
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct MonitoredCall {
+    destination_display: Option<String>,
+    aimed_arrival_time: Option<String>,
+    expected_arrival_time: Option<String>
+}
@@ -0,0 +1,81 @@
+use super::*;
+use crate::AppState;
+use smart_leds::RGB8;
+mod siri_fixtures;
+static LED_COLOR: RGB8 = RGB8 {r: 16, g: 16, b: 16};
+static DELAY_COLOR: RGB8 = RGB8 {r: 16, g: 10, b: 0};
+
+fn provider(line_refs: &[&str]) -> SiriProvider {
+    SiriProvider::new("KEY", "SF", "15553", line_refs.iter().map(|line| line.to_string()).collect())
+}
+
+#[test]
+fn test_stop_visits() {
+    let payload = siri_fixtures::json_with_stop_visits("2024-05-09T23:28:30Z", "2024-05-09T23:39:10Z").unwrap();
+    let snapshot = provider(&[]).parse_departures(&payload).unwrap();
+    let states: Vec<(&str, DepartureState)> = snapshot.departures
+        .iter()
+        .map(|departure| (departure.abbreviation.as_str(), departure.state))
+        .collect();
+    assert_eq!(states, [
+        ("14", DepartureState::Minutes(4)),
+        ("14R", DepartureState::Minutes(15)),
+        ("49", DepartureState::Minutes(5)),
+        ("14", DepartureState::Minutes(22)),
+    ]);
+
+    let first = &snapshot.departures[0];
+    assert_eq!(first.destination, "Downtown");
+    assert_eq!(first.direction, Direction::Inbound);
+    assert_eq!(first.delay, Duration::from_secs(30));
+    //Not monitored, so only the timetable time and no delay
+    let van_ness = &snapshot.departures[2];
+    assert_eq!(van_ness.direction, Direction::Outbound);
+    assert_eq!(van_ness.delay, Duration::ZERO);
+    //511 sends a null DirectionRef for some trips
+    assert_eq!(snapshot.departures[3].direction, Direction::Unknown);
+}
+
+#[test]
+fn test_line_filter() {
+    let payload = siri_fixtures::json_with_stop_visits("2024-05-09T23:28:30Z", "2024-05-09T23:39:10Z").unwrap();
+    let snapshot = provider(&["14"]).parse_departures(&payload).unwrap();
+    let states: Vec<DepartureState> = snapshot.departures.iter().map(|departure| departure.state).collect();
+    assert_eq!(states, [DepartureState::Minutes(4), DepartureState::Minutes(22)]);
+}
+
+#[test]
+fn test_arriving_bus_is_leaving() {
+    let payload = siri_fixtures::json_with_stop_visits("2024-05-09T23:24:30Z", "2024-05-09T23:39:10Z").unwrap();
+    let snapshot = provider(&["14"]).parse_departures(&payload).unwrap();
+    assert_eq!(snapshot.departures[0].state, DepartureState::Leaving);
+}
+
+#[test]
+fn test_utc_offset_timestamps() {
+    let payload = siri_fixtures::json_with_stop_visits("2024-05-09T16:28:30-07:00", "2024-05-09T16:39:10.500-07:00").unwrap();
+    let snapshot = provider(&["14", "14R"]).parse_departures(&payload).unwrap();
+    let states: Vec<DepartureState> = snapshot.departures.iter().map(|departure| departure.state).collect();
+    assert_eq!(states, [DepartureState::Minutes(4), DepartureState::Minutes(15), DepartureState::Minutes(22)]);
+}
+
+#[test]
+fn test_no_stop_visits() {
+    let mut app_state = AppState::new(Duration::new(0, 0), Box::new(provider(&[])));
+    app_state.received_http_response(siri_fixtures::json_no_stop_visits(), 0);
+    assert_eq!(app_state.service_status(), crate::ServiceStatus::NoService);
+}
+
+#[test]
+fn test_rings_from_stop_visits() {
+    let mut app_state = AppState::new(Duration::new(0, 0), Box::new(provider(&["14", "14R"])));
+    app_state.received_http_response(siri_fixtures::json_with_stop_visits("2024-05-09T23:28:30Z", "2024-05-09T23:39:10Z"), 0);
+    let led_buffer = app_state.get_current_led_buffer(0);
+
+    let mut expected_buffer: [RGB8; 44] = [RGB8::default(); 44];
+    //The 15 min bus is running 6 min behind its timetable
+    expected_buffer[24..28].clone_from_slice(&[LED_COLOR; 4]);
+    expected_buffer[..9].clone_from_slice(&[LED_COLOR; 9]);
+    expected_buffer[9..15].clone_from_slice(&[DELAY_COLOR; 6]);
+    assert_eq!(expected_buffer, led_buffer.rgb_buffer);
+}
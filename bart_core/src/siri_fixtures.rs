@@ -0,0 +1,193 @@
+use anyhow::Result;
+
+//511 really does send a byte order mark in front of its JSON
+const BOM: &str = "\u{feff}";
+
+pub fn json_with_stop_visits(first: &str, second: &str) -> Result<Vec<u8>> {
+    Ok(format!(r##"{}{{
+"ServiceDelivery": {{
+"ResponseTimestamp": "2024-05-09T23:24:03Z",
+"ProducerRef": "SF",
+"Status": true,
+"StopMonitoringDelivery": {{
+"version": "1.4",
+"ResponseTimestamp": "2024-05-09T23:24:03Z",
+"Status": true,
+"MonitoredStopVisit": [
+{{
+"RecordedAtTime": "2024-05-09T23:23:41Z",
+"MonitoringRef": "15553",
+"MonitoredVehicleJourney": {{
+"LineRef": "14",
+"DirectionRef": "IB",
+"FramedVehicleJourneyRef": {{
+"DataFrameRef": "2024-05-09",
+"DatedVehicleJourneyRef": "11551234"
+}},
+"PublishedLineName": "MISSION",
+"OperatorRef": "SF",
+"OriginRef": "14125",
+"OriginName": "Mission St & Cotter St",
+"DestinationRef": "17342",
+"DestinationName": "Downtown",
+"Monitored": true,
+"InCongestion": null,
+"VehicleLocation": {{
+"Longitude": "-122.418732",
+"Latitude": "37.7595291"
+}},
+"Bearing": "0.0000000000",
+"Occupancy": "seatsAvailable",
+"VehicleRef": "8823",
+"MonitoredCall": {{
+"StopPointRef": "15553",
+"StopPointName": "Mission St & 16th St",
+"VehicleLocationAtStop": "",
+"VehicleAtStop": "",
+"DestinationDisplay": "Downtown",
+"AimedArrivalTime": "2024-05-09T23:28:00Z",
+"ExpectedArrivalTime": "{}",
+"AimedDepartureTime": "2024-05-09T23:28:00Z",
+"ExpectedDepartureTime": null,
+"Distances": ""
+}}
+}}
+}},
+{{
+"RecordedAtTime": "2024-05-09T23:23:41Z",
+"MonitoringRef": "15553",
+"MonitoredVehicleJourney": {{
+"LineRef": "14R",
+"DirectionRef": "IB",
+"FramedVehicleJourneyRef": {{
+"DataFrameRef": "2024-05-09",
+"DatedVehicleJourneyRef": "11551301"
+}},
+"PublishedLineName": "MISSION RAPID",
+"OperatorRef": "SF",
+"OriginRef": "14125",
+"OriginName": "Mission St & Cotter St",
+"DestinationRef": "17342",
+"DestinationName": "Downtown",
+"Monitored": true,
+"InCongestion": null,
+"VehicleLocation": {{
+"Longitude": "-122.418732",
+"Latitude": "37.7595291"
+}},
+"Bearing": "0.0000000000",
+"Occupancy": "seatsAvailable",
+"VehicleRef": "8823",
+"MonitoredCall": {{
+"StopPointRef": "15553",
+"StopPointName": "Mission St & 16th St",
+"VehicleLocationAtStop": "",
+"VehicleAtStop": "",
+"DestinationDisplay": "Downtown",
+"AimedArrivalTime": "2024-05-09T23:33:00Z",
+"ExpectedArrivalTime": "{}",
+"AimedDepartureTime": "2024-05-09T23:33:00Z",
+"ExpectedDepartureTime": null,
+"Distances": ""
+}}
+}}
+}},
+{{
+"RecordedAtTime": "2024-05-09T23:23:41Z",
+"MonitoringRef": "15553",
+"MonitoredVehicleJourney": {{
+"LineRef": "49",
+"DirectionRef": "OB",
+"FramedVehicleJourneyRef": {{
+"DataFrameRef": "2024-05-09",
+"DatedVehicleJourneyRef": "11562210"
+}},
+"PublishedLineName": "VAN NESS-MISSION",
+"OperatorRef": "SF",
+"OriginRef": "14125",
+"OriginName": "Mission St & Cotter St",
+"DestinationRef": "17342",
+"DestinationName": "City College",
+"Monitored": true,
+"InCongestion": null,
+"VehicleLocation": {{
+"Longitude": "-122.418732",
+"Latitude": "37.7595291"
+}},
+"Bearing": "0.0000000000",
+"Occupancy": "seatsAvailable",
+"VehicleRef": "8823",
+"MonitoredCall": {{
+"StopPointRef": "15553",
+"StopPointName": "Mission St & 16th St",
+"VehicleLocationAtStop": "",
+"VehicleAtStop": "",
+"DestinationDisplay": "City College",
+"AimedArrivalTime": "2024-05-09T23:30:00Z",
+"ExpectedArrivalTime": null,
+"AimedDepartureTime": "2024-05-09T23:30:00Z",
+"ExpectedDepartureTime": null,
+"Distances": ""
+}}
+}}
+}},
+{{
+"RecordedAtTime": "2024-05-09T23:23:41Z",
+"MonitoringRef": "15553",
+"MonitoredVehicleJourney": {{
+"LineRef": "14",
+"DirectionRef": null,
+"FramedVehicleJourneyRef": {{
+"DataFrameRef": "2024-05-09",
+"DatedVehicleJourneyRef": "11551235"
+}},
+"PublishedLineName": "MISSION",
+"OperatorRef": "SF",
+"OriginRef": "14125",
+"OriginName": "Mission St & Cotter St",
+"DestinationRef": "17342",
+"DestinationName": "Downtown",
+"Monitored": true,
+"InCongestion": null,
+"VehicleLocation": {{
+"Longitude": "-122.418732",
+"Latitude": "37.7595291"
+}},
+"Bearing": "0.0000000000",
+"Occupancy": "seatsAvailable",
+"VehicleRef": "8823",
+"MonitoredCall": {{
+"StopPointRef": "15553",
+"StopPointName": "Mission St & 16th St",
+"VehicleLocationAtStop": "",
+"VehicleAtStop": "",
+"DestinationDisplay": "Downtown",
+"AimedArrivalTime": "2024-05-09T23:45:00Z",
+"ExpectedArrivalTime": "2024-05-09T23:46:30Z",
+"AimedDepartureTime": "2024-05-09T23:45:00Z",
+"ExpectedDepartureTime": null,
+"Distances": ""
+}}
+}}
+}}
+]
+}}
+}}
+}}"##, BOM, first, second).into_bytes())
+}
+
+pub fn json_no_stop_visits() -> Result<Vec<u8>> {
+    Ok(format!(r##"{}{{
+"ServiceDelivery": {{
+"ResponseTimestamp": "2024-05-09T23:24:03Z",
+"ProducerRef": "SF",
+"Status": true,
+"StopMonitoringDelivery": {{
+"version": "1.4",
+"ResponseTimestamp": "2024-05-09T23:24:03Z",
+"Status": true,
+"MonitoredStopVisit": []
+}}
+}}
+}}"##, BOM).into_bytes())
+}
@@ -0,0 +1,232 @@
+//Turns the parts of a static GTFS feed we care about into a compact timetable.
+//build.rs includes this file and time.rs directly, so it can only depend on std and time.rs.
+use std::collections::{HashMap, HashSet};
+
+use crate::time::{days_from_civil, weekday};
+#[cfg(test)]
+#[path = "static_gtfs.test.rs"]
+mod tests;
+
+/// Departure minutes are packed into a u16 with the GTFS direction_id in the top bit.
+/// Service days run past midnight (25:10:00), so minutes go beyond 1440.
+pub const DIRECTION_BIT: u16 = 0x8000;
+
+pub struct GtfsFilter {
+    /// Matches either a stop or its parent station
+    pub stop_ids: Vec<String>,
+    /// Empty for both directions
+    pub direction_ids: Vec<u8>,
+}
+
+/// One week of departures, Sunday first. Each departure is its packed minute and an index into `destinations`.
+#[derive(Debug, Default, PartialEq)]
+pub struct CompactTimetable {
+    pub days: [Vec<(u16, u8)>; 7],
+    /// The station each trip ends at, the parent station when its last stop has one
+    pub destinations: Vec<String>,
+}
+
+impl CompactTimetable {
+    pub fn to_rust_source(&self) -> String {
+        let days: Vec<String> = self.days.iter().map(|day| format!("&{:?}", day)).collect();
+        format!(
+            "static DESTINATIONS: [&str; {}] = {:?};\nstatic DAYS: [&[(u16, u8)]; 7] = [{}];\n",
+            self.destinations.len(), self.destinations, days.join(", "),
+        )
+    }
+}
+
+/// `20240703` as days since the epoch
+pub fn parse_date(date: &str) -> Option<i64> {
+    let date = date.trim();
+    if date.len() != 8 {
+        return None;
+    }
+    let (month, day) = (date[4..6].parse().ok()?, date[6..].parse().ok()?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    Some(days_from_civil(date[..4].parse().ok()?, month, day))
+}
+
+/// Builds the week starting `first_day` (days since the epoch). Each day gets the services calendar.txt runs that weekday
+/// while the date is inside their start_date and end_date, plus calendar_dates.txt's additions and minus its removals.
+/// Overlapping schedule periods don't double up since only the one in effect on each date counts.
+pub fn build_timetable(stops: &str, trips: &str, stop_times: &str, calendar: &str, calendar_dates: &str, filter: &GtfsFilter, first_day: i64) -> Result<CompactTimetable, String> {
+    let mut stop_ids = HashSet::new();
+    let mut stations = HashMap::new();
+    let stops = Csv::parse(stops)?;
+    for row in stops.rows() {
+        let stop_id = stops.field(&row, "stop_id")?;
+        let parent_station = stops.field(&row, "parent_station").unwrap_or("");
+        if filter.stop_ids.iter().any(|wanted| wanted == stop_id || wanted == parent_station) {
+            stop_ids.insert(stop_id.to_string());
+        }
+        let station = if parent_station.is_empty() { stop_id } else { parent_station };
+        stations.insert(stop_id.to_string(), station.to_string());
+    }
+
+    const DAY_COLUMNS: [&str; 7] = ["sunday", "monday", "tuesday", "wednesday", "thursday", "friday", "saturday"];
+    //The dates being built, by weekday
+    let mut week = [0; 7];
+    for day in first_day..first_day + 7 {
+        week[weekday(day) as usize] = day;
+    }
+    let mut service_days: HashMap<String, [bool; 7]> = HashMap::new();
+    let calendar = Csv::parse(calendar)?;
+    for row in calendar.rows() {
+        let start = parse_date(calendar.field(&row, "start_date")?).ok_or("bad start_date")?;
+        let end = parse_date(calendar.field(&row, "end_date")?).ok_or("bad end_date")?;
+        let mut runs = [false; 7];
+        for (weekday, column) in DAY_COLUMNS.iter().enumerate() {
+            runs[weekday] = calendar.field(&row, column)? == "1" && (start..=end).contains(&week[weekday]);
+        }
+        service_days.insert(calendar.field(&row, "service_id")?.to_string(), runs);
+    }
+    //calendar_dates.txt is optional, exception_type 1 adds a service on a date and 2 removes it
+    if !calendar_dates.trim().is_empty() {
+        let calendar_dates = Csv::parse(calendar_dates)?;
+        for row in calendar_dates.rows() {
+            let date = parse_date(calendar_dates.field(&row, "date")?).ok_or("bad date")?;
+            let Some(weekday) = week.iter().position(|day| *day == date) else {
+                continue;
+            };
+            let runs = service_days.entry(calendar_dates.field(&row, "service_id")?.to_string()).or_default();
+            match calendar_dates.field(&row, "exception_type")? {
+                "1" => runs[weekday] = true,
+                "2" => runs[weekday] = false,
+                other => return Err(format!("unknown exception_type {}", other)),
+            }
+        }
+    }
+
+    let mut trip_services: HashMap<String, (String, u8)> = HashMap::new();
+    let trips = Csv::parse(trips)?;
+    for row in trips.rows() {
+        let direction_id: u8 = trips.field(&row, "direction_id").unwrap_or("0").parse().unwrap_or(0);
+        if !filter.direction_ids.is_empty() && !filter.direction_ids.contains(&direction_id) {
+            continue;
+        }
+        trip_services.insert(trips.field(&row, "trip_id")?.to_string(), (trips.field(&row, "service_id")?.to_string(), direction_id));
+    }
+
+    //Departures wait for the whole file since a trip's last stop can come after the stop we're interested in
+    let mut departures = Vec::new();
+    let mut last_stops: HashMap<String, (u32, String)> = HashMap::new();
+    let stop_times = Csv::parse(stop_times)?;
+    for (index, row) in stop_times.rows().enumerate() {
+        let trip_id = stop_times.field(&row, "trip_id")?;
+        let stop_id = stop_times.field(&row, "stop_id")?;
+        //Without stop_sequence the file order is the trip order
+        let sequence = stop_times.field(&row, "stop_sequence").ok().and_then(|sequence| sequence.parse().ok()).unwrap_or(index as u32);
+        match last_stops.get_mut(trip_id) {
+            Some(last_stop) if last_stop.0 >= sequence => {}
+            Some(last_stop) => *last_stop = (sequence, stop_id.to_string()),
+            None => {
+                last_stops.insert(trip_id.to_string(), (sequence, stop_id.to_string()));
+            }
+        }
+        if !stop_ids.contains(stop_id) {
+            continue;
+        }
+        //pickup_type 1 is the end of the line, nobody can board
+        if stop_times.field(&row, "pickup_type").unwrap_or("0") == "1" {
+            continue;
+        }
+        let Some((service_id, direction_id)) = trip_services.get(trip_id) else {
+            continue;
+        };
+        let Some(runs) = service_days.get(service_id) else {
+            continue;
+        };
+        let Some(minute) = parse_minutes(stop_times.field(&row, "departure_time")?) else {
+            continue;
+        };
+        let packed = minute | if *direction_id == 1 { DIRECTION_BIT } else { 0 };
+        departures.push((trip_id.to_string(), packed, *runs));
+    }
+
+    let mut timetable = CompactTimetable::default();
+    for (trip_id, packed, runs) in departures {
+        let last_stop = &last_stops[&trip_id].1;
+        let destination = stations.get(last_stop).unwrap_or(last_stop);
+        let destination_index = match timetable.destinations.iter().position(|known| known == destination) {
+            Some(index) => index,
+            None => {
+                timetable.destinations.push(destination.clone());
+                timetable.destinations.len() - 1
+            }
+        };
+        let destination_index = u8::try_from(destination_index).map_err(|_| "more than 256 destinations")?;
+        for (day, runs) in timetable.days.iter_mut().zip(runs) {
+            if runs {
+                day.push((packed, destination_index));
+            }
+        }
+    }
+    for day in timetable.days.iter_mut() {
+        //Minute order, with same minute entries ordered by direction too so every duplicate ends up next to its twin
+        day.sort_by_key(|(packed, destination)| (packed & !DIRECTION_BIT, *packed, *destination));
+        day.dedup();
+    }
+    Ok(timetable)
+}
+
+/// `25:10:00` is 1510
+fn parse_minutes(time: &str) -> Option<u16> {
+    let mut parts = time.trim().split(':');
+    let hours: u16 = parts.next()?.parse().ok()?;
+    let minutes: u16 = parts.next()?.parse().ok()?;
+    let total = hours * 60 + minutes;
+    if total >= DIRECTION_BIT {
+        return None;
+    }
+    Some(total)
+}
+
+struct Csv<'a> {
+    columns: HashMap<String, usize>,
+    body: &'a str,
+}
+
+impl<'a> Csv<'a> {
+    fn parse(contents: &'a str) -> Result<Csv<'a>, String> {
+        let contents = contents.trim_start_matches('\u{feff}');
+        let (header, body) = contents.split_once('\n').unwrap_or((contents, ""));
+        let columns = split_line(header)
+            .into_iter()
+            .enumerate()
+            .map(|(index, name)| (name.trim().to_string(), index))
+            .collect();
+        Ok(Csv { columns, body })
+    }
+
+    fn rows(&self) -> impl Iterator<Item = Vec<String>> + 'a {
+        self.body.lines().filter(|line| !line.trim().is_empty()).map(split_line)
+    }
+
+    fn field<'r>(&self, row: &'r [String], column: &str) -> Result<&'r str, String> {
+        let index = self.columns.get(column).ok_or_else(|| format!("missing column {}", column))?;
+        Ok(row.get(*index).map(|value| value.trim()).unwrap_or(""))
+    }
+}
+
+fn split_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.trim_end_matches('\r').chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
@@ -0,0 +1,145 @@
+use super::*;
+
+static STOPS: &str = "\u{feff}stop_id,stop_name,parent_station\r
+ROCK,Rockridge,\r
+ROCK-1,\"Rockridge, Platform 1\",ROCK\r
+ROCK-2,\"Rockridge, Platform 2\",ROCK\r
+MCAR,MacArthur,\r
+LAKE,Lake Merritt,\r
+";
+
+static TRIPS: &str = "route_id,service_id,trip_id,direction_id
+1,WKDY,T1,0
+1,WKDY,T2,1
+1,SAT,T3,0
+1,SUN,T4,1
+1,WKDY,T5,0
+";
+
+static STOP_TIMES: &str = "trip_id,arrival_time,departure_time,stop_id,stop_sequence,pickup_type
+T1,07:04:00,07:05:00,ROCK-1,5,0
+T1,07:09:00,07:10:00,MCAR,6,0
+T2,07:12:00,07:12:30,ROCK-2,7,0
+T2,07:20:00,07:20:00,LAKE,8,1
+T3,08:00:00,08:00:00,ROCK-1,5,0
+T4,24:40:00,24:41:00,ROCK-2,3,0
+T5,06:59:00,06:59:00,ROCK-1,9,1
+";
+
+static CALENDAR: &str = "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date
+WKDY,1,1,1,1,1,0,0,20240101,20251231
+SAT,0,0,0,0,0,1,0,20240101,20251231
+SUN,0,0,0,0,0,0,1,20240101,20251231
+";
+
+fn filter(stop_ids: &[&str], direction_ids: Vec<u8>) -> GtfsFilter {
+    GtfsFilter { stop_ids: stop_ids.iter().map(|id| id.to_string()).collect(), direction_ids }
+}
+
+//A Sunday
+fn july_7() -> i64 {
+    parse_date("20240707").unwrap()
+}
+
+fn weekdays(departures: &[(u16, u8)]) -> [Vec<(u16, u8)>; 7] {
+    let mut days: [Vec<(u16, u8)>; 7] = Default::default();
+    for day in &mut days[1..6] {
+        *day = departures.to_vec();
+    }
+    days
+}
+
+#[test]
+fn test_parent_station_both_directions() {
+    let timetable = build_timetable(STOPS, TRIPS, STOP_TIMES, CALENDAR, "", &filter(&["ROCK"], Vec::new()), july_7()).unwrap();
+    //T5 terminates at ROCK so it can't be boarded, T3 and T4 end at one of its platforms
+    let mut days = weekdays(&[(425, 0), (432 | DIRECTION_BIT, 1)]);
+    days[0] = vec![(1481 | DIRECTION_BIT, 2)];
+    days[6] = vec![(480, 2)];
+    assert_eq!(timetable, CompactTimetable { days, destinations: vec!["MCAR".to_string(), "LAKE".to_string(), "ROCK".to_string()] });
+}
+
+#[test]
+fn test_direction_filter() {
+    let timetable = build_timetable(STOPS, TRIPS, STOP_TIMES, CALENDAR, "", &filter(&["ROCK"], vec![1]), july_7()).unwrap();
+    assert_eq!(timetable.days[1], [(432 | DIRECTION_BIT, 0)]);
+    assert!(timetable.days[6].is_empty());
+    assert_eq!(timetable.destinations, ["LAKE", "ROCK"]);
+}
+
+#[test]
+fn test_single_platform() {
+    let timetable = build_timetable(STOPS, TRIPS, STOP_TIMES, CALENDAR, "", &filter(&["MCAR"], Vec::new()), july_7()).unwrap();
+    assert_eq!(timetable.days[1], [(430, 0)]);
+}
+
+#[test]
+fn test_interleaved_duplicates() {
+    //Three trips share 07:05, the inbound ones on either side of the outbound one
+    let trips = "route_id,service_id,trip_id,direction_id\n1,WKDY,A,1\n1,WKDY,B,0\n1,WKDY,C,1\n";
+    let stop_times = "trip_id,departure_time,stop_id\nA,07:05:00,ROCK-1\nB,07:05:00,ROCK-1\nC,07:05:00,ROCK-1\n";
+    let timetable = build_timetable(STOPS, trips, stop_times, CALENDAR, "", &filter(&["ROCK"], Vec::new()), july_7()).unwrap();
+    assert_eq!(timetable.days[1], [(425, 0), (425 | DIRECTION_BIT, 0)]);
+}
+
+#[test]
+fn test_single_weekday_service() {
+    let calendar = "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date\nWKDY,0,0,0,0,1,0,0,20240101,20251231\n";
+    let timetable = build_timetable(STOPS, TRIPS, STOP_TIMES, calendar, "", &filter(&["ROCK"], Vec::new()), july_7()).unwrap();
+    assert_eq!(timetable.days[5], [(425, 0), (432 | DIRECTION_BIT, 1)]);
+    assert!(timetable.days.iter().enumerate().all(|(weekday, day)| weekday == 5 || day.is_empty()));
+}
+
+#[test]
+fn test_overlapping_service_periods() {
+    //The new schedule takes over on Wednesday 2024-07-10 and moves T1 to 07:20
+    let trips = "route_id,service_id,trip_id,direction_id\n1,OLD,T1,0\n1,NEW,T6,0\n1,EXPIRED,T7,0\n";
+    let stop_times = "trip_id,departure_time,stop_id\nT1,07:05:00,ROCK-1\nT6,07:20:00,ROCK-1\nT7,09:00:00,ROCK-1\n";
+    let calendar = "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date
+OLD,1,1,1,1,1,0,0,20240101,20240709
+NEW,1,1,1,1,1,0,0,20240710,20251231
+EXPIRED,1,1,1,1,1,1,1,20230101,20231231
+";
+    let timetable = build_timetable(STOPS, trips, stop_times, calendar, "", &filter(&["ROCK"], Vec::new()), july_7()).unwrap();
+    let mut days = weekdays(&[(440, 0)]);
+    days[1] = vec![(425, 0)];
+    days[2] = vec![(425, 0)];
+    assert_eq!(timetable.days, days);
+}
+
+#[test]
+fn test_calendar_dates() {
+    //Thursday 2024-07-04 runs the Sunday schedule instead of the weekday one
+    let calendar_dates = "service_id,date,exception_type\nWKDY,20240704,2\nSUN,20240704,1\nWKDY,20240801,2\n";
+    let july_1 = parse_date("20240701").unwrap();
+    let timetable = build_timetable(STOPS, TRIPS, STOP_TIMES, CALENDAR, calendar_dates, &filter(&["ROCK"], Vec::new()), july_1).unwrap();
+    assert_eq!(timetable.days[3], [(425, 0), (432 | DIRECTION_BIT, 1)]);
+    assert_eq!(timetable.days[4], [(1481 | DIRECTION_BIT, 2)]);
+    assert_eq!(timetable.days[0], [(1481 | DIRECTION_BIT, 2)]);
+}
+
+#[test]
+fn test_parse_date() {
+    assert_eq!(parse_date("19700101"), Some(0));
+    assert_eq!(parse_date("20240707"), Some(19911));
+    assert_eq!(parse_date("20241301"), None);
+    assert_eq!(parse_date("2024-07-07"), None);
+}
+
+#[test]
+fn test_missing_column() {
+    let result = build_timetable(STOPS, "route_id,trip_id\n1,T1\n", STOP_TIMES, CALENDAR, "", &filter(&["ROCK"], Vec::new()), july_7());
+    assert_eq!(result, Err("missing column service_id".to_string()));
+}
+
+#[test]
+fn test_rust_source() {
+    let mut days: [Vec<(u16, u8)>; 7] = Default::default();
+    days[0] = vec![(1481, 0)];
+    days[1] = vec![(425, 1), (432 | DIRECTION_BIT, 0)];
+    let timetable = CompactTimetable { days, destinations: vec!["ROCK".to_string(), "MCAR".to_string()] };
+    assert_eq!(
+        timetable.to_rust_source(),
+        "static DESTINATIONS: [&str; 2] = [\"ROCK\", \"MCAR\"];\nstatic DAYS: [&[(u16, u8)]; 7] = [&[(1481, 0)], &[(425, 1), (33200, 0)], &[], &[], &[], &[], &[]];\n",
+    );
+}
@@ -0,0 +1,165 @@
+//Just enough calendar math to turn the timestamps transit APIs send into unix seconds and local wall clock time
+#[cfg(test)]
+#[path = "time.test.rs"]
+mod tests;
+
+/// Days since 1970-01-01 for a proleptic Gregorian date (Howard Hinnant's days_from_civil).
+pub(crate) fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// Parses `2024-05-09T23:24:03Z`, `2024-05-09T16:24:03-07:00` or `2024-05-09T16:24:03.250-07:00` into unix seconds.
+pub(crate) fn parse_rfc3339(value: &str) -> Option<i64> {
+    let value = value.trim();
+    let (date, time) = value.split_once(['T', 't', ' '])?;
+    let mut date_parts = date.splitn(3, '-');
+    let year: i64 = date_parts.next()?.parse().ok()?;
+    let month: u32 = date_parts.next()?.parse().ok()?;
+    let day: u32 = date_parts.next()?.parse().ok()?;
+
+    let (clock, offset_sec) = if let Some(clock) = time.strip_suffix(['Z', 'z']) {
+        (clock, 0)
+    } else {
+        let sign_index = time.rfind(['+', '-'])?;
+        let (clock, offset) = time.split_at(sign_index);
+        let sign = if offset.starts_with('-') { -1 } else { 1 };
+        let (offset_hours, offset_minutes) = offset[1..].split_once(':')?;
+        let offset_hours: i64 = offset_hours.parse().ok()?;
+        let offset_minutes: i64 = offset_minutes.parse().ok()?;
+        (clock, sign * (offset_hours * 3600 + offset_minutes * 60))
+    };
+    //Fractional seconds don't matter at a one LED per minute resolution
+    let clock = clock.split('.').next()?;
+    let mut clock_parts = clock.splitn(3, ':');
+    let hours: i64 = clock_parts.next()?.parse().ok()?;
+    let minutes: i64 = clock_parts.next()?.parse().ok()?;
+    let seconds: i64 = clock_parts.next()?.parse().ok()?;
+
+    let days = days_from_civil(year, month, day);
+    Some(days * 86400 + hours * 3600 + minutes * 60 + seconds - offset_sec)
+}
+
+/// Parses BART's `05/09/2024` and `04:24:03 PM PDT` into unix seconds. Only knows the Pacific zone names BART uses.
+pub(crate) fn parse_bart_timestamp(date: &str, time: &str) -> Option<i64> {
+    let mut date_parts = date.trim().splitn(3, '/');
+    let month: u32 = date_parts.next()?.parse().ok()?;
+    let day: u32 = date_parts.next()?.parse().ok()?;
+    let year: i64 = date_parts.next()?.parse().ok()?;
+
+    let mut time_parts = time.split_whitespace();
+    let mut clock_parts = time_parts.next()?.splitn(3, ':');
+    let meridiem = time_parts.next()?;
+    let offset_sec = match time_parts.next()? {
+        "PDT" => -7 * 3600,
+        "PST" => -8 * 3600,
+        _ => return None,
+    };
+    let hours: i64 = clock_parts.next()?.parse().ok()?;
+    let minutes: i64 = clock_parts.next()?.parse().ok()?;
+    let seconds: i64 = clock_parts.next()?.parse().ok()?;
+    if !(1..=12).contains(&hours) {
+        return None;
+    }
+    let afternoon = if meridiem.eq_ignore_ascii_case("PM") { 12 } else { 0 };
+    let hours = hours % 12 + afternoon;
+
+    let days = days_from_civil(year, month, day);
+    Some(days * SECONDS_PER_DAY + hours * 3600 + minutes * 60 + seconds - offset_sec)
+}
+
+const SECONDS_PER_DAY: i64 = 86400;
+
+/// A fixed UTC offset, optionally following the US daylight saving rules (second Sunday in March to first Sunday in November).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeZone {
+    pub utc_offset_min: i32,
+    pub us_daylight_saving: bool,
+}
+
+impl TimeZone {
+    pub fn new(utc_offset_min: i32, us_daylight_saving: bool) -> TimeZone {
+        TimeZone { utc_offset_min, us_daylight_saving }
+    }
+
+    pub fn pacific() -> TimeZone {
+        TimeZone::new(-8 * 60, true)
+    }
+
+    pub fn local_time(&self, unix_sec: i64) -> LocalTime {
+        let standard_offset_sec = self.utc_offset_min as i64 * 60;
+        let offset_sec = if self.us_daylight_saving && self.is_daylight_saving(unix_sec) {
+            standard_offset_sec + 3600
+        } else {
+            standard_offset_sec
+        };
+        LocalTime::from_local_sec(unix_sec + offset_sec)
+    }
+
+    fn is_daylight_saving(&self, unix_sec: i64) -> bool {
+        let standard_offset_sec = self.utc_offset_min as i64 * 60;
+        let year = LocalTime::from_local_sec(unix_sec + standard_offset_sec).year;
+        //Both switches happen at 2am local time, which is still standard time in March and already daylight time in November
+        let start = (nth_sunday(year, 3, 2) * SECONDS_PER_DAY) + 2 * 3600 - standard_offset_sec;
+        let end = (nth_sunday(year, 11, 1) * SECONDS_PER_DAY) + 2 * 3600 - (standard_offset_sec + 3600);
+        unix_sec >= start && unix_sec < end
+    }
+}
+
+/// Days since the epoch of the nth Sunday in a month
+fn nth_sunday(year: i64, month: u32, n: i64) -> i64 {
+    let first = days_from_civil(year, month, 1);
+    let days_to_sunday = (7 - weekday(first)) % 7;
+    first + days_to_sunday + (n - 1) * 7
+}
+
+/// 0 is Sunday, 1970-01-01 was a Thursday
+pub(crate) fn weekday(days_since_epoch: i64) -> i64 {
+    (days_since_epoch + 4).rem_euclid(7)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocalTime {
+    pub year: i64,
+    /// 0 is Sunday
+    pub weekday: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+}
+
+impl LocalTime {
+    fn from_local_sec(local_sec: i64) -> LocalTime {
+        let days = local_sec.div_euclid(SECONDS_PER_DAY);
+        let second_of_day = local_sec.rem_euclid(SECONDS_PER_DAY) as u32;
+        LocalTime {
+            year: civil_year(days),
+            weekday: weekday(days) as u32,
+            hour: second_of_day / 3600,
+            minute: second_of_day / 60 % 60,
+            second: second_of_day % 60,
+        }
+    }
+
+    pub fn minute_of_day(&self) -> u32 {
+        self.hour * 60 + self.minute
+    }
+}
+
+/// Inverse of days_from_civil, only the year is needed
+fn civil_year(days_since_epoch: i64) -> i64 {
+    let days = days_since_epoch + 719468;
+    let era = if days >= 0 { days } else { days - 146096 } / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let year = year_of_era + era * 400;
+    //The era starts in March, so January and February belong to the next civil year
+    if month_index >= 10 { year + 1 } else { year }
+}
@@ -0,0 +1,57 @@
+use super::*;
+
+fn clock(local_time: LocalTime) -> (u32, u32, u32, u32) {
+    (local_time.weekday, local_time.hour, local_time.minute, local_time.second)
+}
+
+#[test]
+fn test_rfc3339_offsets() {
+    assert_eq!(parse_rfc3339("2024-05-09T23:24:03Z"), Some(1715297043));
+    assert_eq!(parse_rfc3339("2024-05-09T16:24:03.250-07:00"), Some(1715297043));
+    assert_eq!(parse_rfc3339("not a time"), None);
+}
+
+#[test]
+fn test_bart_timestamp() {
+    assert_eq!(parse_bart_timestamp("05/09/2024", "04:24:03 PM PDT"), Some(1715297043));
+    assert_eq!(parse_bart_timestamp("01/15/2024", "12:30:00 PM PST"), Some(1705350600));
+    assert_eq!(parse_bart_timestamp("01/15/2024", "12:05:00 AM PST"), Some(1705305900));
+    assert_eq!(parse_bart_timestamp("05/09/2024", "04:24:03 PM EDT"), None);
+    assert_eq!(parse_bart_timestamp("", ""), None);
+}
+
+#[test]
+fn test_pacific_standard_time() {
+    //2024-01-15 20:30:00 UTC
+    let local_time = TimeZone::pacific().local_time(1705350600);
+    assert_eq!(local_time.year, 2024);
+    assert_eq!(clock(local_time), (1, 12, 30, 0));
+}
+
+#[test]
+fn test_spring_forward() {
+    let pacific = TimeZone::pacific();
+    assert_eq!(clock(pacific.local_time(1710064799)), (0, 1, 59, 59));
+    assert_eq!(clock(pacific.local_time(1710064800)), (0, 3, 0, 0));
+}
+
+#[test]
+fn test_fall_back() {
+    let pacific = TimeZone::pacific();
+    assert_eq!(clock(pacific.local_time(1730624399)), (0, 1, 59, 59));
+    assert_eq!(clock(pacific.local_time(1730624400)), (0, 1, 0, 0));
+}
+
+#[test]
+fn test_fixed_offset_ignores_daylight_saving() {
+    let arizona = TimeZone::new(-7 * 60, false);
+    assert_eq!(clock(arizona.local_time(1720000000)), (3, 2, 46, 40));
+}
+
+#[test]
+fn test_new_years_eve() {
+    //2024-01-01 07:59:59 UTC is still 2023 in California
+    let local_time = TimeZone::pacific().local_time(1704095999);
+    assert_eq!(local_time.year, 2023);
+    assert_eq!(clock(local_time), (0, 23, 59, 59));
+}